@@ -9,7 +9,7 @@ use bevy::{
     },
     window::CursorGrabMode,
 };
-use hexasphere_organized::Coordinate;
+use hexasphere_organized::{geometry_util, Coordinate};
 
 fn setup_sphere_object(
     mut commands: Commands,
@@ -17,37 +17,29 @@ fn setup_sphere_object(
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
     const SUBDIV: usize = 32;
-    let geometry = hexasphere::shapes::IcoSphere::new(SUBDIV, |_| ());
-
-    let indices = geometry.get_all_indices();
-
-    let geometry_points = geometry.raw_points();
-
-    let (organized, new_geometry, color_data) = hexasphere_organized::Hexasphere::make_and_dual(
-        SUBDIV,
-        &indices,
-        geometry_points,
-        |geometry_data| {
-            (0..geometry_data.points.len())
-                .map(|_| [1.0; 4])
-                .collect::<Vec<_>>()
-        },
-        |index, edges, coord, geometry_data, color_data| {
-            let color = match coord {
-                Coordinate::Top => Vec4::new(1.0, 1.0, 0.0, 1.0),
-                Coordinate::Bottom => Vec4::new(1.0, 0.0, 1.0, 1.0),
-                Coordinate::Inside { chunk, short, long } => {
-                    let chunk = f32::from(chunk);
-                    let short = short as f32;
-                    let long = long as f32;
-                    Vec4::new(chunk, short, long, 1.0).normalize()
-                }
-            };
-            // println!("index: {index}, coord: {coord:?} color: {color:?}, edges: {edges:?}");
-            color_data[index as usize] = color.into();
-            (index, edges)
-        },
-    );
+
+    let organized = hexasphere_organized::Hexasphere::from_subdivisions(SUBDIV, |coord| {
+        let color = match coord {
+            Coordinate::Top => Vec4::new(1.0, 1.0, 0.0, 1.0),
+            Coordinate::Bottom => Vec4::new(1.0, 0.0, 1.0, 1.0),
+            Coordinate::Inside { chunk, short, long } => {
+                let chunk = f32::from(chunk);
+                let short = short as f32;
+                let long = long as f32;
+                Vec4::new(chunk, short, long, 1.0).normalize()
+            }
+        };
+        <[f32; 4]>::from(color)
+    });
+
+    // Each tile gets its own vertices, so its colour doesn't bleed into its neighbours.
+    let (mut new_geometry, tiles) = geometry_util::flat_dual(organized.chunked(), 0.0);
+    new_geometry.tile_uvs(organized.chunked(), &tiles);
+    let has_tangents = new_geometry.generate_tangents();
+    let color_data = tiles
+        .iter()
+        .map(|&tile| organized[organized.from_index(tile)])
+        .collect::<Vec<_>>();
 
     let mut mesh = Mesh::new(
         PrimitiveTopology::TriangleList,
@@ -77,8 +69,26 @@ fn setup_sphere_object(
     );
     mesh.insert_attribute(
         Mesh::ATTRIBUTE_UV_0,
-        VertexAttributeValues::Float32x2((0..color_data.len()).map(|_| [0.0; 2]).collect()),
+        VertexAttributeValues::Float32x2(
+            new_geometry
+                .uvs
+                .into_iter()
+                .map(std::convert::Into::into)
+                .collect(),
+        ),
     );
+    if has_tangents {
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_TANGENT,
+            VertexAttributeValues::Float32x4(
+                new_geometry
+                    .tangents
+                    .into_iter()
+                    .map(std::convert::Into::into)
+                    .collect(),
+            ),
+        );
+    }
     mesh.insert_attribute(
         Mesh::ATTRIBUTE_COLOR,
         VertexAttributeValues::Float32x4(color_data),
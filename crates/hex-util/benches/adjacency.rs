@@ -0,0 +1,116 @@
+//! Compares building and using the `HashMap` adjacency map against the flat one,
+//! and computing each tile's neighbours against looking them up in a table.
+//!
+//! Run with `cargo bench -p hexasphere_organized --bench adjacency`.
+
+use glam::Vec3A;
+use hexasphere_organized::adjacency::{Adjacency, NeighbourTable};
+use hexasphere_organized::{geometry_util, make_coordinate_store, Chunked, Coordinate, Hexasphere};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 5;
+
+/// The fastest of a few runs of `f`.
+fn time<R>(mut f: impl FnMut() -> R) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+/// The sphere as an index buffer and points, numbered the way
+/// `make_from_surrounding` expects, with the poles at 0 and 11.
+fn geometry(subdivisions: usize) -> (Vec<u32>, Vec<Vec3A>) {
+    let hsphere = Chunked::new(subdivisions);
+    let old = |x: Coordinate| match hsphere.to_index(x) {
+        1 => 11,
+        11 => 1,
+        i => i,
+    };
+
+    let mut indices = Vec::new();
+    let mut points = vec![Vec3A::ZERO; hsphere.tile_count()];
+    for x in hsphere.iter_all() {
+        points[old(x) as usize] = hsphere.center_of(x);
+
+        let around = hsphere.surrounding(x);
+        for (i, &a) in around.iter().enumerate() {
+            indices.extend([old(x), old(a), old(around[(i + 1) % around.len()])]);
+        }
+    }
+
+    (indices, points)
+}
+
+fn main() {
+    println!(
+        "{:>6} | {:>22} | {:>22} | {:>22} | {:>22}",
+        "subdiv",
+        "build map / flat",
+        "organize map / flat",
+        "dual map / flat",
+        "lookup computed / table"
+    );
+
+    for subdivisions in [16, 32, 64, 128, 256] {
+        let (indices, points) = geometry(subdivisions);
+
+        let build_map = time(|| {
+            let mut store = HashMap::new();
+            make_coordinate_store(&indices, &mut store);
+            store
+        });
+        let build_flat = time(|| Adjacency::from_indices(&indices).unwrap());
+
+        let mut map = HashMap::new();
+        make_coordinate_store(&indices, &mut map);
+        let flat = Adjacency::from_indices(&indices).unwrap();
+
+        let organize_map = time(|| Hexasphere::make_from_surrounding(subdivisions, &map, |i, _| i));
+        let organize_flat =
+            time(|| Hexasphere::make_from_surrounding(subdivisions, &flat, |i, _| i));
+
+        let dual_map = time(|| {
+            let vertices = (0..points.len() as u32).map(|x| (x, None));
+            geometry_util::dual(&points, vertices, &map, |_, _, _| {})
+        });
+        let dual_flat = time(|| {
+            let vertices = (0..points.len() as u32).map(|x| (x, None));
+            geometry_util::dual(&points, vertices, &flat, |_, _, _| {})
+        });
+
+        let chunked = Chunked::new(subdivisions);
+        let table = NeighbourTable::new(chunked);
+        let lookup_computed = time(|| {
+            let around = chunked.iter_all().map(|x| chunked.surrounding(x).len());
+            around.sum::<usize>()
+        });
+        let lookup_table = time(|| {
+            let around = chunked.iter_all().map(|x| table.surrounding(x).len());
+            around.sum::<usize>()
+        });
+
+        let pair = |a: Duration, b: Duration| {
+            format!(
+                "{:>7.1?} / {:>7.1?} {:>4.1}x",
+                a,
+                b,
+                a.as_secs_f64() / b.as_secs_f64()
+            )
+        };
+
+        println!(
+            "{subdivisions:>6} | {} | {} | {} | {}",
+            pair(build_map, build_flat),
+            pair(organize_map, organize_flat),
+            pair(dual_map, dual_flat),
+            pair(lookup_computed, lookup_table),
+        );
+    }
+}
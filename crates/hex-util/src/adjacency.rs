@@ -0,0 +1,318 @@
+//! Flat storage for which vertices surround which.
+
+use crate::{Chunked, Coordinate, Error, Hexagonish, Result};
+use arrayvec::ArrayVec;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+
+/// Looks up the vertices around a vertex, in winding order.
+///
+/// This is what [`crate::Hexasphere::make_from_surrounding`] and
+/// [`crate::geometry_util::dual`] need from an adjacency map, so
+/// either a `HashMap` from [`crate::make_coordinate_store`] or an
+/// [`Adjacency`] can be used.
+pub trait Neighbours {
+    /// `None` if `vertex` isn't part of any triangle.
+    fn neighbours(&self, vertex: u32) -> Option<&[u32]>;
+
+    /// The number of vertices with neighbours.
+    fn vertex_count(&self) -> usize;
+}
+
+impl<S: std::hash::BuildHasher> Neighbours for HashMap<u32, Hexagonish<u32>, S> {
+    fn neighbours(&self, vertex: u32) -> Option<&[u32]> {
+        self.get(&vertex).map(ArrayVec::as_slice)
+    }
+
+    fn vertex_count(&self) -> usize {
+        self.len()
+    }
+}
+
+/// An adjacency map stored as one array of neighbours, with
+/// the neighbours of vertex `i` at `offsets[i]..offsets[i + 1]`.
+///
+/// Compared to the `HashMap` from [`crate::make_coordinate_store`]
+/// this avoids hashing on every lookup and packs 5 or 6
+/// neighbours per vertex rather than always 6.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Adjacency {
+    offsets: Vec<u32>,
+    neighbours: Vec<u32>,
+    vertex_count: usize,
+}
+
+impl Adjacency {
+    /// Builds the adjacency map of a triangle index buffer.
+    ///
+    /// The neighbours of each vertex are exactly what
+    /// [`crate::make_coordinate_store`] would give.
+    ///
+    /// Vertex ids are used directly as offsets into the map, so
+    /// they have to be less than `indices.len()`, which holds for any
+    /// buffer where every vertex is in a triangle. This keeps a stray
+    /// id from sizing the map far beyond the buffer it came from.
+    ///
+    /// # Errors
+    /// As in [`crate::try_make_coordinate_store`], and
+    /// [`Error::MalformedIndices`] if an id is too large.
+    pub fn from_indices(indices: &[u32]) -> Result<Self> {
+        check_indices(indices)?;
+
+        let len = indices.iter().max().map_or(0, |&x| x as usize + 1);
+
+        // Bucket the triangles by vertex, keeping them in order.
+        let mut starts = vec![0u32; len + 1];
+        for &x in indices {
+            starts[x as usize + 1] += 1;
+        }
+        for i in 0..len {
+            starts[i + 1] += starts[i];
+        }
+
+        let mut filled = starts.clone();
+        let mut pairs = vec![[0, 0]; indices.len()];
+        for x in indices.chunks(3) {
+            let &[a, b, c] = x else { unreachable!() };
+            for (i, j, k) in [(a, b, c), (b, c, a), (c, a, b)] {
+                pairs[filled[i as usize] as usize] = [j, k];
+                filled[i as usize] += 1;
+            }
+        }
+
+        let mut offsets = Vec::with_capacity(len + 1);
+        let mut neighbours = Vec::with_capacity(indices.len());
+        let mut vertex_count = 0;
+        offsets.push(0);
+
+        for vertex in 0..len {
+            let mut list = Hexagonish::new();
+            for &pair in &pairs[starts[vertex] as usize..starts[vertex + 1] as usize] {
+                link(&mut list, vertex as u32, pair)?;
+            }
+            vertex_count += usize::from(!list.is_empty());
+            neighbours.extend(list);
+            offsets.push(neighbours.len() as u32);
+        }
+
+        Ok(Self {
+            offsets,
+            neighbours,
+            vertex_count,
+        })
+    }
+
+    /// Like [`Adjacency::from_indices`], but buckets
+    /// and links the triangles on many threads.
+    ///
+    /// # Errors
+    /// As in [`Adjacency::from_indices`], although
+    /// which error is found first may differ.
+    #[cfg(feature = "rayon")]
+    pub fn par_from_indices(indices: &[u32]) -> Result<Self> {
+        use rayon::prelude::*;
+
+        check_indices(indices)?;
+
+        // Sorting by triangle as well keeps each vertex's
+        // triangles in the order `from_indices` links them.
+        let mut pairs = indices
+            .par_chunks(3)
+            .enumerate()
+            .flat_map_iter(|(triangle, x)| {
+                let &[a, b, c] = x else { unreachable!() };
+                [(a, b, c), (b, c, a), (c, a, b)]
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(turn, (i, j, k))| ((i, triangle, turn), [j, k]))
+            })
+            .collect::<Vec<_>>();
+        pairs.par_sort_unstable_by_key(|&(key, _)| key);
+
+        let lists = pairs
+            .par_chunk_by(|(x, _), (y, _)| x.0 == y.0)
+            .map(|group| {
+                let vertex = group[0].0 .0;
+                let mut list = Hexagonish::new();
+                for &(_, pair) in group {
+                    link(&mut list, vertex, pair)?;
+                }
+                Ok((vertex, list))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let len = lists.last().map_or(0, |&(x, _)| x as usize + 1);
+        let mut lists = lists.iter().peekable();
+        Ok(Self::from_lists((0..len as u32).map(|vertex| {
+            lists
+                .next_if(|(x, _)| *x == vertex)
+                .map_or(&[][..], |(_, list)| list.as_slice())
+        })))
+    }
+
+    /// Builds the adjacency map where vertex `i` is
+    /// surrounded by the `i`th list, in winding order.
+    pub fn from_lists<L: AsRef<[u32]>>(lists: impl IntoIterator<Item = L>) -> Self {
+        let mut offsets = vec![0];
+        let mut neighbours = Vec::new();
+        let mut vertex_count = 0;
+
+        for list in lists {
+            let list = list.as_ref();
+            vertex_count += usize::from(!list.is_empty());
+            neighbours.extend_from_slice(list);
+            offsets.push(neighbours.len() as u32);
+        }
+
+        Self {
+            offsets,
+            neighbours,
+            vertex_count,
+        }
+    }
+
+    /// One more than the largest vertex id, including ones without neighbours.
+    pub(crate) fn id_count(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    /// Where the neighbours of `vertex` are in `neighbours`.
+    fn span(&self, vertex: u32) -> std::ops::Range<usize> {
+        self.offsets[vertex as usize] as usize..self.offsets[vertex as usize + 1] as usize
+    }
+
+    /// The vertex ids which have neighbours, in increasing order.
+    pub fn vertices(&self) -> impl Iterator<Item = u32> + '_ {
+        self.offsets
+            .windows(2)
+            .enumerate()
+            .filter(|(_, x)| x[0] != x[1])
+            .map(|(i, _)| i as u32)
+    }
+}
+
+impl Neighbours for Adjacency {
+    fn neighbours(&self, vertex: u32) -> Option<&[u32]> {
+        let start = *self.offsets.get(vertex as usize)? as usize;
+        let end = *self.offsets.get(vertex as usize + 1)? as usize;
+        (start != end).then(|| &self.neighbours[start..end])
+    }
+
+    fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+}
+
+impl<S: std::hash::BuildHasher> From<&HashMap<u32, Hexagonish<u32>, S>> for Adjacency {
+    fn from(value: &HashMap<u32, Hexagonish<u32>, S>) -> Self {
+        let len = value.keys().max().map_or(0, |&x| x + 1);
+        Self::from_lists((0..len).map(|x| value.get(&x).map_or(&[][..], |x| x.as_slice())))
+    }
+}
+
+/// Every tile's neighbours, worked out once up front.
+///
+/// [`Chunked::surrounding`] recomputes the neighbours on every call,
+/// which is cheap but adds up in searches and floods that ask about
+/// the same tiles over and over. This answers with a slice instead,
+/// at the cost of around 170 bytes per tile, so it is opt-in; see
+/// [`crate::Hexasphere::cache_neighbours`].
+///
+/// Tiles are also numbered densely by [`Chunked::to_index`], and
+/// the table doubles as an [`Adjacency`] over those ids.
+#[derive(Clone, PartialEq, Eq)]
+pub struct NeighbourTable {
+    chunked: Chunked,
+    ids: Adjacency,
+    coordinates: Vec<Coordinate>,
+}
+
+impl NeighbourTable {
+    pub fn new(chunked: Chunked) -> Self {
+        // `iter_all` goes in index order.
+        let coordinates = chunked
+            .iter_all()
+            .flat_map(|x| chunked.surrounding(x))
+            .collect::<Vec<_>>();
+        let ids = Adjacency::from_lists(chunked.iter_all().map(|x| {
+            let around = chunked.surrounding(x).into_iter();
+            around
+                .map(|y| chunked.to_index(y))
+                .collect::<Hexagonish<_>>()
+        }));
+
+        Self {
+            chunked,
+            ids,
+            coordinates,
+        }
+    }
+
+    pub fn chunked(&self) -> Chunked {
+        self.chunked
+    }
+
+    /// The same as [`Chunked::surrounding`].
+    ///
+    /// # Panics
+    /// If `x` is not on this sphere.
+    pub fn surrounding(&self, x: Coordinate) -> &[Coordinate] {
+        &self.coordinates[self.ids.span(self.chunked.to_index(x))]
+    }
+
+    /// The ids of the tiles around the tile with id `id`,
+    /// with ids as given by [`Chunked::to_index`].
+    ///
+    /// # Panics
+    /// If `id` is not on this sphere.
+    pub fn surrounding_ids(&self, id: u32) -> &[u32] {
+        &self.ids.neighbours[self.ids.span(id)]
+    }
+
+    /// The neighbours of every tile by id, such as
+    /// for [`crate::geometry_util::dual`].
+    pub fn ids(&self) -> &Adjacency {
+        &self.ids
+    }
+}
+
+impl Debug for NeighbourTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NeighbourTable")
+            .field("subdivisions", &self.chunked.subdivisions())
+            .field("tiles", &self.ids.vertex_count)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Checks that `indices` is whole triangles, with every
+/// id small enough for [`Adjacency::from_indices`].
+fn check_indices(indices: &[u32]) -> Result<()> {
+    let len = indices.len();
+    if len % 3 != 0 || indices.iter().any(|&x| x as usize >= len) {
+        return Err(Error::MalformedIndices { len });
+    }
+
+    Ok(())
+}
+
+/// Adds the triangle `vertex, j, k` to the list of what surrounds `vertex`,
+/// keeping the list in winding order and without duplicates.
+pub(crate) fn link(list: &mut Hexagonish<u32>, vertex: u32, [j, k]: [u32; 2]) -> Result<()> {
+    let too_many = |_| Error::TooManyNeighbours { vertex };
+
+    if list.is_empty() {
+        list.extend([j, k]);
+    } else if let Some(idx_j) = list.iter().position(|&z| z == j) {
+        if list[(idx_j + 1) % list.len()] != k {
+            list.try_insert(idx_j + 1, k).map_err(too_many)?;
+        }
+    } else if let Some(idx_k) = list.iter().position(|&z| z == k) {
+        list.try_insert(idx_k, j).map_err(too_many)?;
+    } else {
+        list.try_push(j).map_err(too_many)?;
+        list.try_push(k).map_err(too_many)?;
+    }
+
+    Ok(())
+}
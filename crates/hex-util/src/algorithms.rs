@@ -1,6 +1,9 @@
 use crate::{Coordinate, Hexagonish, Hexasphere};
 use glam::Vec3A;
 use pathfinding::prelude::astar as ext_astar;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::f32::consts::{PI, TAU};
 
 ///
 /// Runs the `A*` algorithm from the `pathfinding` crate.
@@ -34,6 +37,329 @@ pub fn astar<T>(
     )
 }
 
+///
+/// Runs Dijkstra's algorithm out from every tile in `sources` at once.
+///
+/// - `sources` are where the cost is 0.
+/// - `sphere` is the grid system upon which to run it.
+/// - `weight(sphere, X, Y)` is the cost of moving from tile X to tile Y, or
+///     `None` if the movement is impossible, as in [`astar`].
+/// - `max_cost` stops the search at tiles costing more than it to reach.
+///
+/// Returns the cheapest cost to reach each tile from any source,
+/// or `None` where no source can reach it within `max_cost`.
+/// Costs which don't fit in a `u32` count as out of reach.
+///
+/// # Panics
+/// If a source is not on `sphere`.
+///
+pub fn distance_field<T>(
+    sources: impl IntoIterator<Item = Coordinate>,
+    sphere: &Hexasphere<T>,
+    mut weight: impl FnMut(&Hexasphere<T>, Coordinate, Coordinate) -> Option<usize>,
+    max_cost: Option<u32>,
+) -> Hexasphere<Option<u32>> {
+    let mut costs = sphere.change_type(|_| None);
+    let mut frontier = BinaryHeap::new();
+
+    for source in sources {
+        costs[source] = Some(0);
+        frontier.push(Reverse((0, source)));
+    }
+
+    relax(
+        sphere,
+        &mut costs,
+        frontier,
+        |x, next| weight(sphere, x, next),
+        max_cost.unwrap_or(u32::MAX),
+        |_, _| {},
+    );
+
+    costs
+}
+
+/// Which way to step from each tile to get to the nearest of a set
+/// of goals, for moving many units to the same place at once.
+///
+/// Unlike [`distance_field`], `weight(sphere, X, Y)` is the cost of
+/// stepping from X towards the goals through Y, since that's the
+/// way everything moves along the field.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowField {
+    goals: Vec<Coordinate>,
+    costs: Hexasphere<Option<u32>>,
+    directions: Hexasphere<Option<Coordinate>>,
+}
+
+impl FlowField {
+    /// # Panics
+    /// If a goal is not on `sphere`.
+    pub fn new<T>(
+        goals: impl IntoIterator<Item = Coordinate>,
+        sphere: &Hexasphere<T>,
+        weight: impl FnMut(&Hexasphere<T>, Coordinate, Coordinate) -> Option<usize>,
+    ) -> Self {
+        let mut field = Self {
+            goals: goals.into_iter().collect(),
+            costs: sphere.change_type(|_| None),
+            directions: sphere.change_type(|_| None),
+        };
+
+        let goals = field.goals.clone();
+        field.flow(
+            sphere,
+            goals.into_iter().map(|x| Reverse((0, x))).collect(),
+            weight,
+        );
+        field
+    }
+
+    /// Where to step from each tile, or `None` at the
+    /// goals and where no goal can be reached.
+    pub fn directions(&self) -> &Hexasphere<Option<Coordinate>> {
+        &self.directions
+    }
+
+    /// The cost of following the field to a goal from each tile.
+    pub fn costs(&self) -> &Hexasphere<Option<u32>> {
+        &self.costs
+    }
+
+    pub fn goals(&self) -> &[Coordinate] {
+        &self.goals
+    }
+
+    /// Brings the field up to date after the cost of moving into or out of
+    /// the tiles in `changed` has changed, with `weight` giving the new costs.
+    ///
+    /// Only the tiles which were flowing through `changed`, and any which
+    /// can now do better through them, are worked out again. Costs come
+    /// out the same as building the field again, but between equally
+    /// good steps this may pick a different one.
+    pub fn update<T>(
+        &mut self,
+        sphere: &Hexasphere<T>,
+        changed: impl IntoIterator<Item = Coordinate>,
+        weight: impl FnMut(&Hexasphere<T>, Coordinate, Coordinate) -> Option<usize>,
+    ) {
+        // Everything flowing through a changed tile has to find a new way.
+        let mut stale = changed.into_iter().collect::<HashSet<_>>();
+        let mut to_check = stale.iter().copied().collect::<Vec<_>>();
+        while let Some(x) = to_check.pop() {
+            for around in sphere.surrounding(x) {
+                if self.directions[around] == Some(x) && stale.insert(around) {
+                    to_check.push(around);
+                }
+            }
+        }
+
+        for &x in &stale {
+            self.costs[x] = None;
+            self.directions[x] = None;
+        }
+
+        // Then flow back in from the goals and everything left around them.
+        let mut frontier = BinaryHeap::new();
+        for &goal in &self.goals {
+            if stale.contains(&goal) {
+                self.costs[goal] = Some(0);
+                frontier.push(Reverse((0, goal)));
+            }
+        }
+        for &x in &stale {
+            for around in sphere.surrounding(x) {
+                if let Some(cost) = self.costs[around].filter(|_| !stale.contains(&around)) {
+                    frontier.push(Reverse((cost as usize, around)));
+                }
+            }
+        }
+
+        self.flow(sphere, frontier, weight);
+    }
+
+    fn flow<T>(
+        &mut self,
+        sphere: &Hexasphere<T>,
+        frontier: BinaryHeap<Reverse<(usize, Coordinate)>>,
+        mut weight: impl FnMut(&Hexasphere<T>, Coordinate, Coordinate) -> Option<usize>,
+    ) {
+        for &goal in &self.goals {
+            self.costs[goal] = Some(0);
+        }
+
+        let directions = &mut self.directions;
+        relax(
+            sphere,
+            &mut self.costs,
+            frontier,
+            |x, from| weight(sphere, from, x),
+            u32::MAX,
+            |from, x| directions[from] = Some(x),
+        );
+    }
+}
+
+/// Dijkstra's algorithm from `frontier`, where `step(X, Y)` is
+/// the cost of going from X to Y, and `reached(Y, X)` is called
+/// whenever a cheaper way to Y is found through X.
+fn relax<T>(
+    sphere: &Hexasphere<T>,
+    costs: &mut Hexasphere<Option<u32>>,
+    mut frontier: BinaryHeap<Reverse<(usize, Coordinate)>>,
+    mut step: impl FnMut(Coordinate, Coordinate) -> Option<usize>,
+    max_cost: u32,
+    mut reached: impl FnMut(Coordinate, Coordinate),
+) {
+    let max_cost = max_cost as usize;
+
+    while let Some(Reverse((cost, x))) = frontier.pop() {
+        if costs[x].is_some_and(|best| (best as usize) < cost) {
+            continue;
+        }
+
+        for next in sphere.surrounding(x) {
+            let Some(step) = step(x, next) else {
+                continue;
+            };
+            let next_cost = cost.saturating_add(step);
+            if next_cost > max_cost || costs[next].is_some_and(|best| best as usize <= next_cost) {
+                continue;
+            }
+
+            costs[next] = Some(next_cost as u32);
+            reached(next, x);
+            frontier.push(Reverse((next_cost, next)));
+        }
+    }
+}
+
+///
+/// Whether `to` can be seen from `from`, following [`crate::Chunked::line`].
+///
+/// Only the tiles strictly between the two can block the view,
+/// so a blocking tile can still be seen itself.
+///
+pub fn line_of_sight<T>(
+    sphere: &Hexasphere<T>,
+    from: Coordinate,
+    to: Coordinate,
+    blocks: impl Fn(Coordinate) -> bool,
+) -> bool {
+    sphere
+        .line(from, to)
+        .filter(|&x| x != from && x != to)
+        .all(|x| !blocks(x))
+}
+
+///
+/// Finds the tiles visible from `origin` at most `radius` steps away.
+///
+/// This casts shadows ring by ring: looking out from `origin`, each
+/// visible tile which `blocks` hides everything further out behind it.
+/// A tile is visible if any part of it is out of the shadows. Since the
+/// angles are measured on the sphere itself, the pentagons need no special
+/// handling, and walls meeting only at a corner still can't be seen through.
+///
+/// `origin` is always visible, and never blocks the view itself.
+///
+pub fn field_of_view<T>(
+    sphere: &Hexasphere<T>,
+    origin: Coordinate,
+    radius: usize,
+    blocks: impl Fn(Coordinate) -> bool,
+) -> HashSet<Coordinate> {
+    let center = sphere.center_of(origin);
+    let right = center.any_orthonormal_vector();
+    let up = center.cross(right);
+    let bearing = |x: Vec3A| x.dot(up).atan2(x.dot(right));
+
+    let mut visible = HashSet::from([origin]);
+    let mut shadows = Shadows::default();
+
+    for ring in 1..=radius {
+        if shadows.is_full() {
+            break;
+        }
+
+        let mut cast = Vec::new();
+        for x in sphere.ring(origin, ring) {
+            // The smallest span of bearings covering the tile's corners.
+            let mid = bearing(sphere.center_of(x));
+            let (low, high) = sphere
+                .corners_of(x)
+                .into_iter()
+                .map(|corner| (bearing(corner) - mid + PI).rem_euclid(TAU) - PI)
+                .fold((0.0f32, 0.0f32), |(low, high), d| (low.min(d), high.max(d)));
+            let span = (mid + low, mid + high);
+
+            if !shadows.covers(span) {
+                visible.insert(x);
+                if blocks(x) {
+                    cast.push(span);
+                }
+            }
+        }
+
+        // Tiles on the same ring don't hide each other.
+        for span in cast {
+            shadows.add(span);
+        }
+    }
+
+    visible
+}
+
+/// Ranges of bearings in `-PI..=PI` which are hidden, kept
+/// sorted and merged so each bearing is in at most one.
+#[derive(Default)]
+struct Shadows(Vec<(f32, f32)>);
+
+impl Shadows {
+    /// How far into a shadow a tile has to be to
+    /// count as hidden, for the sake of rounding.
+    const EPSILON: f32 = 1e-4;
+
+    /// Splits a span of bearings which may wrap around.
+    fn split((low, high): (f32, f32)) -> impl Iterator<Item = (f32, f32)> {
+        let shift = ((low + PI) / TAU).floor() * TAU;
+        let (low, high) = (low - shift, high - shift);
+        if high > PI {
+            [Some((low, PI)), Some((-PI, high - TAU))]
+        } else {
+            [Some((low, high)), None]
+        }
+        .into_iter()
+        .flatten()
+    }
+
+    fn covers(&self, span: (f32, f32)) -> bool {
+        Self::split(span).all(|(low, high)| {
+            self.0
+                .iter()
+                .any(|&(a, b)| a <= low + Self::EPSILON && high - Self::EPSILON <= b)
+        })
+    }
+
+    fn add(&mut self, span: (f32, f32)) {
+        self.0.extend(Self::split(span));
+        self.0.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut merged = Vec::<(f32, f32)>::with_capacity(self.0.len());
+        for (low, high) in self.0.drain(..) {
+            match merged.last_mut() {
+                Some(last) if low <= last.1 => last.1 = last.1.max(high),
+                _ => merged.push((low, high)),
+            }
+        }
+        self.0 = merged;
+    }
+
+    fn is_full(&self) -> bool {
+        self.covers((-PI, PI))
+    }
+}
+
 pub fn find_point<T>(
     point_on_sphere: Vec3A,
     sphere: &Hexasphere<T>,
@@ -0,0 +1,314 @@
+//! Edges and corners between tiles, for things like rivers and
+//! roads which run along the borders of tiles rather than across them.
+
+use crate::{Chunked, Coordinate, Hexagonish};
+use glam::Vec3A;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::ops::{Deref, Index, IndexMut};
+
+/// The border between two adjacent tiles,
+/// the same whichever way round they're given.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Edge([Coordinate; 2]);
+
+impl Edge {
+    /// This doesn't check that `a` and `b` are adjacent;
+    /// see [`Chunked::is_edge`].
+    pub fn new(a: Coordinate, b: Coordinate) -> Self {
+        let mut tiles = [a, b];
+        tiles.sort_unstable();
+        Self(tiles)
+    }
+
+    /// The tiles on either side, in increasing order.
+    pub fn tiles(self) -> [Coordinate; 2] {
+        self.0
+    }
+
+    pub fn contains(self, x: Coordinate) -> bool {
+        self.0.contains(&x)
+    }
+
+    /// The tile on the other side from `x`, if `x` is on either side.
+    pub fn other(self, x: Coordinate) -> Option<Coordinate> {
+        match self.0 {
+            [a, b] if a == x => Some(b),
+            [a, b] if b == x => Some(a),
+            _ => None,
+        }
+    }
+}
+
+/// Where three tiles meet, which is a vertex of the dual geometry,
+/// the same whichever order they're given in.
+///
+/// This is the same grouping as the dual uses to share its vertices.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Corner([Coordinate; 3]);
+
+impl Corner {
+    /// This doesn't check that the tiles meet;
+    /// see [`Chunked::is_corner`].
+    pub fn new(a: Coordinate, b: Coordinate, c: Coordinate) -> Self {
+        let mut tiles = [a, b, c];
+        tiles.sort_unstable();
+        Self(tiles)
+    }
+
+    /// The tiles meeting here, in increasing order.
+    pub fn tiles(self) -> [Coordinate; 3] {
+        self.0
+    }
+
+    pub fn contains(self, x: Coordinate) -> bool {
+        self.0.contains(&x)
+    }
+
+    /// The three edges which meet here.
+    pub fn edges(self) -> [Edge; 3] {
+        let [a, b, c] = self.0;
+        [Edge([a, b]), Edge([a, c]), Edge([b, c])]
+    }
+}
+
+impl Chunked {
+    pub fn edge_count(self) -> usize {
+        3 * self.tile_count() - 6
+    }
+
+    pub fn corner_count(self) -> usize {
+        2 * self.tile_count() - 4
+    }
+
+    /// Whether both sides of `edge` are tiles on this sphere and adjacent.
+    pub fn is_edge(self, edge: Edge) -> bool {
+        let [a, b] = edge.0;
+        self.is_valid(a) && self.is_valid(b) && self.surrounding(a).contains(&b)
+    }
+
+    /// Whether all three tiles of `corner` are on this sphere and meet.
+    pub fn is_corner(self, corner: Corner) -> bool {
+        let [a, b, c] = corner.0;
+        self.is_edge(Edge([a, b])) && self.is_edge(Edge([a, c])) && self.is_edge(Edge([b, c]))
+    }
+
+    /// The edges around a tile, where edge `i` is shared
+    /// with neighbour `i` from [`Chunked::surrounding`].
+    pub fn edges_around(self, x: Coordinate) -> Hexagonish<Edge> {
+        let around = self.surrounding(x);
+        around.into_iter().map(|y| Edge::new(x, y)).collect()
+    }
+
+    /// The corners around a tile, where corner `i` is between
+    /// neighbours `i` and `i + 1`, the same as [`Chunked::corners_of`].
+    pub fn corners_around(self, x: Coordinate) -> Hexagonish<Corner> {
+        let around = self.surrounding(x);
+        (0..around.len())
+            .map(|i| Corner::new(x, around[i], around[(i + 1) % around.len()]))
+            .collect()
+    }
+
+    /// The corners at either end of `edge`, in the winding
+    /// of the corners of its first tile.
+    ///
+    /// # Panics
+    /// If `edge` isn't on this sphere.
+    pub fn edge_corners(self, edge: Edge) -> [Corner; 2] {
+        let [a, b] = edge.0;
+        let around = self.surrounding(a);
+        let i = around
+            .iter()
+            .position(|&x| x == b)
+            .expect("Edge is not between adjacent tiles!");
+        let n = around.len();
+
+        [
+            Corner::new(a, around[(i + n - 1) % n], b),
+            Corner::new(a, b, around[(i + 1) % n]),
+        ]
+    }
+
+    /// The three corners one edge away from `corner`.
+    ///
+    /// # Panics
+    /// If `corner` isn't on this sphere.
+    pub fn corner_neighbours(self, corner: Corner) -> [Corner; 3] {
+        corner.edges().map(|edge| {
+            let [x, y] = self.edge_corners(edge);
+            if x == corner {
+                y
+            } else {
+                x
+            }
+        })
+    }
+
+    /// Where `corner` is on the unit sphere, the same as the dual geometry.
+    pub fn corner_position(self, corner: Corner) -> Vec3A {
+        let [a, b, c] = corner.0;
+        (self.center_of(a) + self.center_of(b) + self.center_of(c)).normalize()
+    }
+
+    /// Every edge once, in the order of [`EdgeMap`]'s storage.
+    pub fn iter_edges(self) -> impl Iterator<Item = Edge> {
+        self.iter_all().flat_map(move |x| Edge::owned_by(self, x))
+    }
+
+    /// Every corner once, in the order of [`CornerMap`]'s storage.
+    pub fn iter_corners(self) -> impl Iterator<Item = Corner> {
+        self.iter_all().flat_map(move |x| Corner::owned_by(self, x))
+    }
+}
+
+/// Something between tiles which [`FeatureMap`] can store data for.
+///
+/// Each is stored with the tile around it which comes
+/// first in [`Chunked::iter_all`], which is said to own it.
+pub trait Feature: Copy + Eq {
+    /// What `tile` owns, in the order they're stored.
+    fn owned_by(chunked: Chunked, tile: Coordinate) -> Hexagonish<Self>;
+
+    /// The tile which owns this.
+    fn owner(self, chunked: Chunked) -> Coordinate;
+}
+
+impl Feature for Edge {
+    fn owned_by(chunked: Chunked, tile: Coordinate) -> Hexagonish<Self> {
+        let index = chunked.to_index(tile);
+        let around = chunked.surrounding(tile).into_iter();
+        around
+            .filter(|&x| chunked.to_index(x) > index)
+            .map(|x| Edge::new(tile, x))
+            .collect()
+    }
+
+    fn owner(self, chunked: Chunked) -> Coordinate {
+        let [a, b] = self.0;
+        std::cmp::min_by_key(a, b, |&x| chunked.to_index(x))
+    }
+}
+
+impl Feature for Corner {
+    fn owned_by(chunked: Chunked, tile: Coordinate) -> Hexagonish<Self> {
+        chunked
+            .corners_around(tile)
+            .into_iter()
+            .filter(|corner| corner.owner(chunked) == tile)
+            .collect()
+    }
+
+    fn owner(self, chunked: Chunked) -> Coordinate {
+        let tiles = self.0.into_iter();
+        tiles.min_by_key(|&x| chunked.to_index(x)).unwrap()
+    }
+}
+
+/// Stores one `T` for every edge or corner of a sphere,
+/// in the same way that [`crate::Hexasphere`] does for tiles.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeatureMap<K, T> {
+    inner: Chunked,
+    /// Where the data for what each tile owns starts, by tile index.
+    starts: Vec<u32>,
+    data: Vec<T>,
+    _feature: std::marker::PhantomData<K>,
+}
+
+/// Data for each [`Edge`] of a sphere.
+pub type EdgeMap<T> = FeatureMap<Edge, T>;
+
+/// Data for each [`Corner`] of a sphere.
+pub type CornerMap<T> = FeatureMap<Corner, T>;
+
+impl<K: Feature, T> FeatureMap<K, T> {
+    /// Creates the storage, with `make` providing the entry for each feature.
+    pub fn from_subdivisions(subdivisions: usize, mut make: impl FnMut(K) -> T) -> Self {
+        let inner = Chunked::new(subdivisions);
+        let mut starts = Vec::with_capacity(inner.tile_count());
+        let mut data = Vec::new();
+
+        for tile in inner.iter_all() {
+            starts.push(data.len() as u32);
+            data.extend(K::owned_by(inner, tile).into_iter().map(&mut make));
+        }
+
+        Self {
+            inner,
+            starts,
+            data,
+            _feature: std::marker::PhantomData,
+        }
+    }
+
+    fn position(&self, feature: K) -> Option<usize> {
+        let owner = feature.owner(self.inner);
+        if !self.inner.is_valid(owner) {
+            return None;
+        }
+
+        let at = K::owned_by(self.inner, owner)
+            .into_iter()
+            .position(|x| x == feature)?;
+        Some(self.starts[self.inner.to_index(owner) as usize] as usize + at)
+    }
+
+    /// The entry for `feature`, or `None` if it isn't on this sphere.
+    pub fn get(&self, feature: K) -> Option<&T> {
+        self.position(feature).map(|i| &self.data[i])
+    }
+
+    pub fn get_mut(&mut self, feature: K) -> Option<&mut T> {
+        self.position(feature).map(|i| &mut self.data[i])
+    }
+
+    /// Every feature and its entry, in storage order.
+    pub fn iter(&self) -> impl Iterator<Item = (K, &T)> + '_ {
+        let inner = self.inner;
+        inner
+            .iter_all()
+            .flat_map(move |x| K::owned_by(inner, x))
+            .zip(&self.data)
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = &T> {
+        self.data.iter()
+    }
+
+    pub fn all_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.data.iter_mut()
+    }
+
+    pub fn change_type<Q>(&self, to: impl FnMut(&T) -> Q) -> FeatureMap<K, Q> {
+        FeatureMap {
+            inner: self.inner,
+            starts: self.starts.clone(),
+            data: self.data.iter().map(to).collect(),
+            _feature: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<K, T> Deref for FeatureMap<K, T> {
+    type Target = Chunked;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<K: Feature, T> Index<K> for FeatureMap<K, T> {
+    type Output = T;
+
+    fn index(&self, index: K) -> &Self::Output {
+        self.get(index).expect("Feature is not on this sphere!")
+    }
+}
+
+impl<K: Feature, T> IndexMut<K> for FeatureMap<K, T> {
+    fn index_mut(&mut self, index: K) -> &mut Self::Output {
+        self.get_mut(index).expect("Feature is not on this sphere!")
+    }
+}
@@ -1,7 +1,32 @@
-#[derive(thiserror::Error, Debug)]
+use crate::Coordinate;
+
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
 pub enum Error {
-    #[error("todo")]
-    Todo,
+    #[error("{coordinate:?} is not on a hexasphere with {subdivisions} subdivisions")]
+    InvalidCoordinate {
+        coordinate: Coordinate,
+        subdivisions: usize,
+    },
+    #[error("{coordinate:?} was asked for more than once")]
+    DuplicateCoordinate { coordinate: Coordinate },
+    #[error("{from:?} and {to:?} are not adjacent")]
+    NotAdjacent { from: Coordinate, to: Coordinate },
+    #[error("the coordinates do not form a ring")]
+    NotARing,
+    #[error("index buffer has {len} indices, which is not a whole number of triangles or too few for its vertex ids")]
+    MalformedIndices { len: usize },
+    #[error("vertex {vertex} is used but has no position or neighbours")]
+    MissingVertex { vertex: u32 },
+    #[error("vertex {vertex} has more than 6 neighbours")]
+    TooManyNeighbours { vertex: u32 },
+    #[error("the winding around vertex {vertex} is inconsistent with its neighbours")]
+    InconsistentWinding { vertex: u32 },
+    #[error("chunk {chunk} has {len} entries, but {expected} are needed")]
+    ChunkLength {
+        chunk: usize,
+        len: usize,
+        expected: usize,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,461 @@
+//! Writing [`GeometryData`] out as Wavefront OBJ or PLY, for looking
+//! at a sphere in other tools.
+//!
+//! Every triangle can be annotated with the tile it's part of, given as
+//! [`FaceTiles`], along with a value for each tile from a [`Hexasphere`]:
+//!
+//! - In OBJ, the triangles of each tile are put in a group named after
+//!   its coordinate, like `inside_2_3_4`, `top` or `bottom`. A value for
+//!   the tile goes in a comment on the line after, like `# height 0.25`.
+//! - In PLY, each face gets a `tile` property with the tile's index as in
+//!   [`Chunked::to_index`], and `chunk`, `short` and `long` properties
+//!   with its coordinate, where `chunk` is `-1` for the top and `-2` for
+//!   the bottom. A value for the tile goes in properties of its own.
+//!
+//! Points get their normals, and UVs if there are any. Both formats
+//! put `v = 0` at the bottom of the texture, so `v` is flipped from
+//! [`GeometryData::uvs`].
+
+use crate::geometry_util::GeometryData;
+use crate::{Chunked, Coordinate, Hexasphere};
+use std::io::{self, BufWriter, Write};
+
+/// Which tile each triangle of a [`GeometryData`] belongs to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FaceTiles {
+    chunked: Chunked,
+    /// By triangle, as in [`Chunked::to_index`].
+    tiles: Vec<u32>,
+}
+
+impl FaceTiles {
+    /// For geometry laid out like [`Hexasphere::make_dual`], with
+    /// one triangle per edge of each tile in the order of
+    /// [`Chunked::iter_all`].
+    pub fn dual(chunked: Chunked) -> Self {
+        let tiles = chunked
+            .iter_all()
+            .flat_map(|x| {
+                let triangles = chunked.surrounding(x).len();
+                std::iter::repeat(chunked.to_index(x)).take(triangles)
+            })
+            .collect();
+        Self { chunked, tiles }
+    }
+
+    /// For geometry where each point belongs to one tile, such as
+    /// from [`crate::geometry_util::flat_dual`] or
+    /// [`crate::geometry_util::prisms`], from the tile of each point.
+    ///
+    /// # Panics
+    /// If `points` is missing a point used by `geometry`.
+    pub fn from_points(chunked: Chunked, geometry: &GeometryData, points: &[u32]) -> Self {
+        let tiles = geometry
+            .indices
+            .chunks(3)
+            .map(|triangle| points[triangle[0] as usize])
+            .collect();
+        Self { chunked, tiles }
+    }
+
+    /// The tile of each triangle, as in [`Chunked::to_index`].
+    pub fn tiles(&self) -> &[u32] {
+        &self.tiles
+    }
+
+    fn coordinate(&self, face: usize) -> Coordinate {
+        self.chunked.from_index(self.tiles[face])
+    }
+}
+
+/// A value for each tile which can be written alongside the geometry.
+pub trait Property {
+    /// The PLY type of each component, such as `float`.
+    const TYPE: &'static str;
+    /// How many values of [`Property::TYPE`] this is written as.
+    const COMPONENTS: usize = 1;
+
+    /// Writes the components as text, separated by spaces.
+    ///
+    /// # Errors
+    /// If `writer` fails.
+    fn write_ascii(&self, writer: &mut impl Write) -> io::Result<()>;
+
+    /// Writes the components as little endian bytes.
+    ///
+    /// # Errors
+    /// If `writer` fails.
+    fn write_binary(&self, writer: &mut impl Write) -> io::Result<()>;
+}
+
+macro_rules! impl_property {
+    ($($ty:ty => $name:literal),*) => {
+        $(
+            impl Property for $ty {
+                const TYPE: &'static str = $name;
+
+                fn write_ascii(&self, writer: &mut impl Write) -> io::Result<()> {
+                    write!(writer, "{self}")
+                }
+
+                fn write_binary(&self, writer: &mut impl Write) -> io::Result<()> {
+                    writer.write_all(&self.to_le_bytes())
+                }
+            }
+        )*
+    };
+}
+
+impl_property!(
+    u8 => "uchar", i8 => "char", u16 => "ushort", i16 => "short",
+    u32 => "uint", i32 => "int", f32 => "float", f64 => "double"
+);
+
+impl Property for bool {
+    const TYPE: &'static str = "uchar";
+
+    fn write_ascii(&self, writer: &mut impl Write) -> io::Result<()> {
+        u8::from(*self).write_ascii(writer)
+    }
+
+    fn write_binary(&self, writer: &mut impl Write) -> io::Result<()> {
+        u8::from(*self).write_binary(writer)
+    }
+}
+
+/// Written as one property per component, such as
+/// `color_0`, `color_1` and `color_2` for a layer called `color`.
+impl<T: Property, const N: usize> Property for [T; N] {
+    const TYPE: &'static str = T::TYPE;
+    const COMPONENTS: usize = T::COMPONENTS * N;
+
+    fn write_ascii(&self, writer: &mut impl Write) -> io::Result<()> {
+        for (i, x) in self.iter().enumerate() {
+            if i > 0 {
+                writer.write_all(b" ")?;
+            }
+            x.write_ascii(writer)?;
+        }
+        Ok(())
+    }
+
+    fn write_binary(&self, writer: &mut impl Write) -> io::Result<()> {
+        self.iter().try_for_each(|x| x.write_binary(writer))
+    }
+}
+
+/// A named value for each tile, to annotate the triangles with.
+#[derive(Debug)]
+pub struct Layer<'a, T> {
+    /// Has to be a single word to be read back.
+    pub name: &'a str,
+    pub values: &'a Hexasphere<T>,
+}
+
+// Not derived, since that would need `T: Copy`.
+impl<T> Clone for Layer<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Layer<'_, T> {}
+
+/// How a PLY file stores its elements.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PlyFormat {
+    #[default]
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// Writes `geometry` to `writer` as OBJ, with a group for each
+/// tile if `faces` is given.
+///
+/// # Errors
+/// If `writer` fails, `geometry` doesn't have one normal per point,
+/// has UVs but not one per point, or `faces` doesn't have one tile
+/// per triangle.
+pub fn write_obj(
+    geometry: &GeometryData,
+    faces: Option<&FaceTiles>,
+    writer: impl Write,
+) -> io::Result<()> {
+    obj::<u8>(geometry, faces, None, writer)
+}
+
+/// Like [`write_obj`], but with the value from `layer`
+/// for each tile after the start of its group.
+///
+/// # Errors
+/// As with [`write_obj`], or if `layer` is for a different sphere.
+pub fn write_obj_with_layer<T: Property>(
+    geometry: &GeometryData,
+    faces: &FaceTiles,
+    layer: Layer<'_, T>,
+    writer: impl Write,
+) -> io::Result<()> {
+    obj(geometry, Some(faces), Some(&layer), writer)
+}
+
+/// Writes `geometry` to `writer` as PLY, with the tile of each
+/// triangle if `faces` is given.
+///
+/// # Errors
+/// If `writer` fails, `geometry` doesn't have one normal per point,
+/// has UVs but not one per point, or `faces` doesn't have one tile
+/// per triangle.
+pub fn write_ply(
+    geometry: &GeometryData,
+    faces: Option<&FaceTiles>,
+    format: PlyFormat,
+    writer: impl Write,
+) -> io::Result<()> {
+    ply::<u8>(geometry, faces, None, format, writer)
+}
+
+/// Like [`write_ply`], but with the value from `layer` for
+/// the tile of each triangle.
+///
+/// # Errors
+/// As with [`write_ply`], or if `layer` is for a different sphere.
+pub fn write_ply_with_layer<T: Property>(
+    geometry: &GeometryData,
+    faces: &FaceTiles,
+    layer: Layer<'_, T>,
+    format: PlyFormat,
+    writer: impl Write,
+) -> io::Result<()> {
+    ply(geometry, Some(faces), Some(&layer), format, writer)
+}
+
+fn check(
+    geometry: &GeometryData,
+    faces: Option<&FaceTiles>,
+    layer: Option<&Layer<'_, impl Property>>,
+) -> io::Result<()> {
+    if geometry.normals.len() != geometry.points.len() {
+        return Err(invalid(format!(
+            "{} normals given for {} points",
+            geometry.normals.len(),
+            geometry.points.len()
+        )));
+    }
+
+    if !geometry.uvs.is_empty() && geometry.uvs.len() != geometry.points.len() {
+        return Err(invalid(format!(
+            "{} uvs given for {} points",
+            geometry.uvs.len(),
+            geometry.points.len()
+        )));
+    }
+
+    if let Some(faces) = faces {
+        if faces.tiles.len() * 3 != geometry.indices.len() {
+            return Err(invalid(format!(
+                "{} tiles given for {} triangles",
+                faces.tiles.len(),
+                geometry.indices.len() / 3
+            )));
+        }
+
+        if let Some(layer) = layer {
+            if layer.values.chunked() != faces.chunked {
+                return Err(invalid("layer is for a different sphere than the faces"));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn obj<T: Property>(
+    geometry: &GeometryData,
+    faces: Option<&FaceTiles>,
+    layer: Option<&Layer<'_, T>>,
+    writer: impl Write,
+) -> io::Result<()> {
+    check(geometry, faces, layer)?;
+    let mut writer = BufWriter::new(writer);
+    let uvs = !geometry.uvs.is_empty();
+
+    for point in &geometry.points {
+        writeln!(writer, "v {} {} {}", point.x, point.y, point.z)?;
+    }
+    for normal in &geometry.normals {
+        writeln!(writer, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+    }
+    if uvs {
+        for uv in &geometry.uvs {
+            // OBJ puts `v = 0` at the bottom.
+            writeln!(writer, "vt {} {}", uv.x, 1.0 - uv.y)?;
+        }
+    }
+
+    let mut group = None;
+    for (face, triangle) in geometry.indices.chunks(3).enumerate() {
+        if let Some(faces) = faces {
+            if group != Some(faces.tiles[face]) {
+                group = Some(faces.tiles[face]);
+                let coordinate = faces.coordinate(face);
+                match coordinate {
+                    Coordinate::Top => writeln!(writer, "g top")?,
+                    Coordinate::Bottom => writeln!(writer, "g bottom")?,
+                    Coordinate::Inside { chunk, short, long } => {
+                        writeln!(writer, "g inside_{chunk}_{short}_{long}")?;
+                    }
+                }
+
+                if let Some(layer) = layer {
+                    write!(writer, "# {} ", layer.name)?;
+                    layer.values[coordinate].write_ascii(&mut writer)?;
+                    writeln!(writer)?;
+                }
+            }
+        }
+
+        write!(writer, "f")?;
+        for &index in triangle {
+            // OBJ counts from 1.
+            let index = index + 1;
+            if uvs {
+                write!(writer, " {index}/{index}/{index}")?;
+            } else {
+                write!(writer, " {index}//{index}")?;
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    writer.flush()
+}
+
+fn ply<T: Property>(
+    geometry: &GeometryData,
+    faces: Option<&FaceTiles>,
+    layer: Option<&Layer<'_, T>>,
+    format: PlyFormat,
+    writer: impl Write,
+) -> io::Result<()> {
+    check(geometry, faces, layer)?;
+    let mut writer = BufWriter::new(writer);
+    let uvs = !geometry.uvs.is_empty();
+
+    writeln!(writer, "ply")?;
+    match format {
+        PlyFormat::Ascii => writeln!(writer, "format ascii 1.0")?,
+        PlyFormat::BinaryLittleEndian => writeln!(writer, "format binary_little_endian 1.0")?,
+    }
+    if let Some(faces) = faces {
+        let subdivisions = faces.chunked.subdivisions();
+        writeln!(writer, "comment hexasphere {subdivisions} subdivisions")?;
+    }
+
+    writeln!(writer, "element vertex {}", geometry.points.len())?;
+    for name in ["x", "y", "z", "nx", "ny", "nz"] {
+        writeln!(writer, "property float {name}")?;
+    }
+    if uvs {
+        writeln!(writer, "property float s")?;
+        writeln!(writer, "property float t")?;
+    }
+
+    writeln!(writer, "element face {}", geometry.indices.len() / 3)?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    if faces.is_some() {
+        writeln!(writer, "property uint tile")?;
+        writeln!(writer, "property char chunk")?;
+        writeln!(writer, "property uint short")?;
+        writeln!(writer, "property uint long")?;
+    }
+    if let Some(layer) = layer {
+        if T::COMPONENTS == 1 {
+            writeln!(writer, "property {} {}", T::TYPE, layer.name)?;
+        } else {
+            for i in 0..T::COMPONENTS {
+                writeln!(writer, "property {} {}_{i}", T::TYPE, layer.name)?;
+            }
+        }
+    }
+    writeln!(writer, "end_header")?;
+
+    let mut row = Row {
+        bytes: Vec::new(),
+        ascii: format == PlyFormat::Ascii,
+    };
+
+    for (i, (point, normal)) in geometry.points.iter().zip(&geometry.normals).enumerate() {
+        for x in [point.x, point.y, point.z, normal.x, normal.y, normal.z] {
+            row.push(&x);
+        }
+        if uvs {
+            // As does PLY.
+            row.push(&geometry.uvs[i].x);
+            row.push(&(1.0 - geometry.uvs[i].y));
+        }
+        row.finish(&mut writer)?;
+    }
+
+    for (face, triangle) in geometry.indices.chunks(3).enumerate() {
+        row.push(&3u8);
+        for index in triangle {
+            row.push(index);
+        }
+
+        if let Some(faces) = faces {
+            let coordinate = faces.coordinate(face);
+            let (chunk, short, long) = match coordinate {
+                Coordinate::Top => (-1, 0, 0),
+                Coordinate::Bottom => (-2, 0, 0),
+                Coordinate::Inside { chunk, short, long } => {
+                    (chunk as i8, short as u32, long as u32)
+                }
+            };
+            row.push(&faces.tiles[face]);
+            row.push(&chunk);
+            row.push(&short);
+            row.push(&long);
+
+            if let Some(layer) = layer {
+                row.push(&layer.values[coordinate]);
+            }
+        }
+
+        row.finish(&mut writer)?;
+    }
+
+    writer.flush()
+}
+
+/// One line of an ASCII PLY file, or the bytes of one element of a binary one.
+struct Row {
+    bytes: Vec<u8>,
+    ascii: bool,
+}
+
+impl Row {
+    fn push(&mut self, value: &impl Property) {
+        let written = if self.ascii {
+            if !self.bytes.is_empty() {
+                self.bytes.push(b' ');
+            }
+            value.write_ascii(&mut self.bytes)
+        } else {
+            value.write_binary(&mut self.bytes)
+        };
+        written.expect("writing to a Vec doesn't fail");
+    }
+
+    fn finish(&mut self, writer: &mut impl Write) -> io::Result<()> {
+        if self.ascii {
+            self.bytes.push(b'\n');
+        }
+        writer.write_all(&self.bytes)?;
+        self.bytes.clear();
+        Ok(())
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.into())
+}
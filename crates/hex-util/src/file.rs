@@ -0,0 +1,363 @@
+//! A compact, versioned binary format for [`Hexasphere`]s.
+//!
+//! A file is laid out as:
+//!
+//! | Bytes       | Contents                                            |
+//! |-------------|-----------------------------------------------------|
+//! | 4           | [`MAGIC`]                                           |
+//! | 2           | [`VERSION`]                                         |
+//! | 1           | [`Compression`]                                     |
+//! | 4           | Subdivisions                                        |
+//! | 5 * (8 + 8) | Offset from the start and length of each chunk      |
+//! | ...         | `top`, then `bottom`                                |
+//! | ...         | The five chunk sections                             |
+//!
+//! Each chunk section starts with its number of entries, and then
+//! either holds the entries one after another, or, when run length
+//! compressed, pairs of a run length and the entry repeated in the run.
+//!
+//! All numbers are little endian. Since the sections are listed
+//! up front, [`read_chunk`] can load a single chunk without the rest.
+//!
+//! Subdivisions are limited to [`MAX_SUBDIVISIONS`]. A single run can
+//! stand for a whole chunk, so the size of a file says nothing about
+//! how much reading it takes, and this is what keeps a forged header
+//! from asking for an unbounded amount of memory.
+
+use crate::{Chunked, Hexasphere};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// The bytes every file starts with.
+pub const MAGIC: [u8; 4] = *b"HXSP";
+/// The version of the format this writes.
+pub const VERSION: u16 = 1;
+/// The most subdivisions a file can have, a little over ten million tiles.
+pub const MAX_SUBDIVISIONS: usize = 1 << 10;
+
+const HEADER_LEN: u64 = 4 + 2 + 1 + 4 + 5 * 16;
+
+/// How the chunk sections are stored.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Compression {
+    #[default]
+    None,
+    /// Good for layers with large areas of the same value, like biome ids.
+    RunLength,
+}
+
+/// A value which can be stored in a file.
+pub trait Element: Sized {
+    /// # Errors
+    /// If `writer` fails.
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()>;
+
+    /// # Errors
+    /// If `reader` fails or doesn't hold a valid value.
+    fn read_from(reader: &mut impl Read) -> io::Result<Self>;
+}
+
+macro_rules! impl_element {
+    ($($ty:ty),*) => {
+        $(
+            impl Element for $ty {
+                fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+                    writer.write_all(&self.to_le_bytes())
+                }
+
+                fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+                    let mut bytes = [0; std::mem::size_of::<$ty>()];
+                    reader.read_exact(&mut bytes)?;
+                    Ok(<$ty>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_element!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl Element for bool {
+    fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        u8::from(*self).write_to(writer)
+    }
+
+    fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        match u8::read_from(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid("bool is neither 0 nor 1")),
+        }
+    }
+}
+
+/// Everything before the poles.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Header {
+    pub version: u16,
+    pub compression: Compression,
+    pub subdivisions: usize,
+    /// Offset from the start of the file and length in bytes of each chunk.
+    pub sections: [(u64, u64); 5],
+}
+
+impl Header {
+    /// # Errors
+    /// If `reader` fails, or isn't at the start of a file this version can read.
+    pub fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid("not a hexasphere file"));
+        }
+
+        let version = u16::read_from(reader)?;
+        if version != VERSION {
+            return Err(invalid(format!("unsupported version {version}")));
+        }
+
+        let compression = match u8::read_from(reader)? {
+            0 => Compression::None,
+            1 => Compression::RunLength,
+            other => return Err(invalid(format!("unknown compression {other}"))),
+        };
+
+        let subdivisions = u32::read_from(reader)? as usize;
+        if subdivisions > MAX_SUBDIVISIONS {
+            return Err(invalid(format!(
+                "{subdivisions} subdivisions is more than the limit of {MAX_SUBDIVISIONS}"
+            )));
+        }
+
+        let mut sections = [(0, 0); 5];
+        for section in &mut sections {
+            *section = (u64::read_from(reader)?, u64::read_from(reader)?);
+        }
+
+        Ok(Self {
+            version,
+            compression,
+            subdivisions,
+            sections,
+        })
+    }
+
+    fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&MAGIC)?;
+        self.version.write_to(writer)?;
+        let compression: u8 = match self.compression {
+            Compression::None => 0,
+            Compression::RunLength => 1,
+        };
+        compression.write_to(writer)?;
+        if self.subdivisions > MAX_SUBDIVISIONS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("more than {MAX_SUBDIVISIONS} subdivisions"),
+            ));
+        }
+        (self.subdivisions as u32).write_to(writer)?;
+
+        for (offset, len) in self.sections {
+            offset.write_to(writer)?;
+            len.write_to(writer)?;
+        }
+
+        Ok(())
+    }
+
+    /// The number of entries each chunk must have.
+    fn chunk_len(&self) -> io::Result<usize> {
+        self.subdivisions
+            .checked_add(1)
+            .and_then(|n| n.checked_mul(n))
+            .and_then(|n| n.checked_mul(2))
+            .ok_or_else(|| invalid("too many subdivisions"))
+    }
+}
+
+/// Writes `hsphere` to `writer`.
+///
+/// # Errors
+/// If `writer` fails, or `hsphere` has more than [`MAX_SUBDIVISIONS`].
+pub fn write<T: Element + PartialEq>(
+    hsphere: &Hexasphere<T>,
+    mut writer: impl Write,
+    compression: Compression,
+) -> io::Result<()> {
+    let mut poles = Vec::new();
+    hsphere.top.write_to(&mut poles)?;
+    hsphere.bottom.write_to(&mut poles)?;
+
+    let mut offset = HEADER_LEN + poles.len() as u64;
+    let mut sections = [(0, 0); 5];
+    let mut contents = Vec::with_capacity(5);
+    for (section, chunk) in sections.iter_mut().zip(&hsphere.chunks) {
+        let bytes = write_section(chunk, compression)?;
+        *section = (offset, bytes.len() as u64);
+        offset += bytes.len() as u64;
+        contents.push(bytes);
+    }
+
+    let header = Header {
+        version: VERSION,
+        compression,
+        subdivisions: hsphere.subdivisions,
+        sections,
+    };
+
+    header.write(&mut writer)?;
+    writer.write_all(&poles)?;
+    for bytes in contents {
+        writer.write_all(&bytes)?;
+    }
+
+    Ok(())
+}
+
+/// Reads a whole hexasphere from `reader`.
+///
+/// # Errors
+/// If `reader` fails, or doesn't hold a valid file. This includes
+/// any chunk not having the size implied by the subdivisions in the header.
+pub fn read<T: Element + Clone>(mut reader: impl Read) -> io::Result<Hexasphere<T>> {
+    let header = Header::read(&mut reader)?;
+    let mut reader = CountingReader {
+        inner: reader,
+        position: HEADER_LEN,
+    };
+
+    let top = T::read_from(&mut reader)?;
+    let bottom = T::read_from(&mut reader)?;
+
+    let mut chunks = [(); 5].map(|()| Vec::new());
+    for (chunk, &(offset, len)) in chunks.iter_mut().zip(&header.sections) {
+        // Reading front to back, sections have to come one after another.
+        let skip = offset
+            .checked_sub(reader.position)
+            .ok_or_else(|| invalid("overlapping chunk sections"))?;
+        io::copy(&mut (&mut reader).take(skip), &mut io::sink())?;
+        *chunk = read_section(&mut reader, &header, len)?;
+    }
+
+    Ok(Hexasphere {
+        inner: Chunked::new(header.subdivisions),
+        top,
+        bottom,
+        chunks,
+        neighbours: None,
+    })
+}
+
+/// Reads only the entries of `chunk`, which is in `0..5`.
+///
+/// # Errors
+/// As with [`read`], but only the header and `chunk` are checked.
+pub fn read_chunk<T: Element + Clone>(
+    mut reader: impl Read + Seek,
+    chunk: u8,
+) -> io::Result<(Header, Vec<T>)> {
+    let start = reader.stream_position()?;
+    let header = Header::read(&mut reader)?;
+    let &(offset, len) = header
+        .sections
+        .get(chunk as usize)
+        .ok_or_else(|| invalid(format!("there is no chunk {chunk}")))?;
+
+    reader.seek(SeekFrom::Start(start + offset))?;
+    let entries = read_section(&mut reader, &header, len)?;
+    Ok((header, entries))
+}
+
+fn write_section<T: Element + PartialEq>(
+    chunk: &[T],
+    compression: Compression,
+) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    (chunk.len() as u64).write_to(&mut bytes)?;
+
+    match compression {
+        Compression::None => {
+            for entry in chunk {
+                entry.write_to(&mut bytes)?;
+            }
+        }
+        Compression::RunLength => {
+            let mut rest = chunk;
+            while let Some(first) = rest.first() {
+                let run = rest.iter().take_while(|&x| x == first).count();
+                (run as u64).write_to(&mut bytes)?;
+                first.write_to(&mut bytes)?;
+                rest = &rest[run..];
+            }
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn read_section<T: Element + Clone>(
+    reader: impl Read,
+    header: &Header,
+    len: u64,
+) -> io::Result<Vec<T>> {
+    let mut reader = reader.take(len);
+    let expected = header.chunk_len()?;
+
+    let count = u64::read_from(&mut reader)?;
+    if count != expected as u64 {
+        return Err(invalid(format!(
+            "chunk has {count} entries, but {} subdivisions needs {expected}",
+            header.subdivisions
+        )));
+    }
+
+    // Every entry takes at least a byte, so the section can't be short.
+    if header.compression == Compression::None && len.saturating_sub(8) < count {
+        return Err(invalid("chunk section is too short for its entries"));
+    }
+
+    // Don't trust the file for how much to allocate up front.
+    let mut entries = Vec::with_capacity(expected.min(1 << 16));
+    match header.compression {
+        Compression::None => {
+            for _ in 0..expected {
+                entries.push(T::read_from(&mut reader)?);
+            }
+        }
+        Compression::RunLength => {
+            while entries.len() < expected {
+                let run = u64::read_from(&mut reader)?;
+                if run == 0 || run > (expected - entries.len()) as u64 {
+                    return Err(invalid("run goes past the end of the chunk"));
+                }
+
+                let entry = T::read_from(&mut reader)?;
+                entries.extend(std::iter::repeat(entry).take(run as usize));
+            }
+        }
+    }
+
+    if reader.limit() != 0 {
+        return Err(invalid("chunk section is longer than its entries"));
+    }
+
+    Ok(entries)
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Keeps track of how far into the file reading has gotten.
+struct CountingReader<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
@@ -1,7 +1,10 @@
-use crate::Hexagonish;
-use glam::Vec3A;
+use crate::adjacency::{Adjacency, Neighbours};
+use crate::{Chunked, Error, Hexagonish, Hexasphere, Result};
+use arrayvec::ArrayVec;
+use glam::{Vec2, Vec3A, Vec4};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use tracing::instrument;
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq)]
 struct UnorderedTrio(u32, u32, u32);
@@ -27,21 +30,260 @@ pub struct GeometryData {
     pub points: Vec<Vec3A>,
     pub normals: Vec<Vec3A>,
     pub indices: Vec<u32>,
+    /// Empty unless filled in by [`GeometryData::spherical_uvs`]
+    /// or [`GeometryData::tile_uvs`].
+    pub uvs: Vec<Vec2>,
+    /// Empty unless filled in by [`GeometryData::generate_tangents`],
+    /// with the sign of the bitangent in `w`.
+    pub tangents: Vec<Vec4>,
 }
 
+impl GeometryData {
+    /// Fills in [`GeometryData::uvs`] by latitude and longitude, with `u`
+    /// going east from longitude `0` and `v` going south from the north pole,
+    /// as in [`Chunked::coordinate_at_latlon`].
+    ///
+    /// Triangles crossing the seam at longitude `0`, or touching a pole, get
+    /// their own copies of the points there so their `u`s don't wrap around.
+    /// The copies go on the end, and are returned as which point each is a
+    /// copy of, so that any other data per point can be copied to match.
+    ///
+    /// Clears [`GeometryData::tangents`], since they depend on the UVs.
+    pub fn spherical_uvs(&mut self) -> Vec<u32> {
+        use std::f32::consts::{PI, TAU};
+
+        let uv = |x: Vec3A| {
+            let x = x.normalize();
+            Vec2::new(
+                ((-x.z).atan2(x.x) / TAU).rem_euclid(1.0),
+                0.5 - x.y.clamp(-1.0, 1.0).asin() / PI,
+            )
+        };
+        let at_pole = |x: Vec3A| x.x.hypot(x.z) <= 1e-6 * x.length();
+
+        self.tangents.clear();
+        self.uvs = self.points.iter().map(|&x| uv(x)).collect();
+        let mut copied = Vec::new();
+        let mut wrapped = HashMap::new();
+
+        let mut copy = |geometry: &mut Self, from: u32, uv: Vec2| {
+            geometry.points.push(geometry.points[from as usize]);
+            geometry.normals.push(geometry.normals[from as usize]);
+            geometry.uvs.push(uv);
+            copied.push(from);
+            (geometry.points.len() - 1) as u32
+        };
+
+        for triangle in 0..self.indices.len() / 3 {
+            let corners = triangle * 3..triangle * 3 + 3;
+            let us = self.indices[corners.clone()]
+                .iter()
+                .filter(|&&x| !at_pole(self.points[x as usize]))
+                .map(|&x| self.uvs[x as usize].x)
+                .collect::<Hexagonish<_>>();
+
+            // Crossing the seam, so move the side near `0` over to near `1`.
+            let crosses = us.iter().copied().fold(f32::MIN, f32::max)
+                - us.iter().copied().fold(f32::MAX, f32::min)
+                > 0.5;
+            let shift = |u: f32| if crosses && u < 0.5 { u + 1.0 } else { u };
+
+            for i in corners.clone() {
+                let x = self.indices[i];
+                let uv = self.uvs[x as usize];
+                if !at_pole(self.points[x as usize]) && crosses && uv.x < 0.5 {
+                    self.indices[i] = *wrapped
+                        .entry(x)
+                        .or_insert_with(|| copy(self, x, Vec2::new(uv.x + 1.0, uv.y)));
+                }
+            }
+
+            // A pole has every `u`, so use the one in the middle of the rest.
+            for i in corners {
+                let x = self.indices[i];
+                if at_pole(self.points[x as usize]) && !us.is_empty() {
+                    let u = us.iter().map(|&u| shift(u)).sum::<f32>() / us.len() as f32;
+                    let uv = Vec2::new(u, self.uvs[x as usize].y);
+                    self.indices[i] = copy(self, x, uv);
+                }
+            }
+        }
+
+        copied
+    }
+
+    /// Fills in [`GeometryData::uvs`] so each tile is mapped onto the
+    /// whole of `0.0..1.0`, for picking a tile's texture out of an atlas.
+    ///
+    /// `tiles` gives the tile of each point, as from [`flat_dual`]
+    /// or [`prisms`], since points can't be shared between tiles here.
+    /// Each tile is seen from straight above with north at the top, and
+    /// its corners touch a circle of diameter `1.0` around `(0.5, 0.5)`.
+    /// Points straight above or below each other share their UVs, so
+    /// the walls of [`prisms`] don't get much of the texture.
+    /// Clears [`GeometryData::tangents`], as in [`GeometryData::spherical_uvs`].
+    ///
+    /// # Panics
+    /// If `tiles` doesn't have a valid tile for each point.
+    pub fn tile_uvs(&mut self, chunked: Chunked, tiles: &[u32]) {
+        assert_eq!(
+            tiles.len(),
+            self.points.len(),
+            "Need a tile for each point!"
+        );
+
+        self.tangents.clear();
+        let mut current = None;
+        let mut frame = (Vec3A::ZERO, Vec3A::ZERO, Vec3A::ZERO, 1.0);
+
+        self.uvs = self
+            .points
+            .iter()
+            .zip(tiles)
+            .map(|(&point, &tile)| {
+                if current != Some(tile) {
+                    current = Some(tile);
+                    let coordinate = chunked.from_index(tile);
+                    let center = chunked.center_of(coordinate);
+                    let north = Vec3A::Y
+                        .reject_from_normalized(center)
+                        .try_normalize()
+                        .unwrap_or(Vec3A::NEG_Z);
+                    let east = north.cross(center);
+
+                    // Looking straight down, everything is projected onto the plane of the tile.
+                    let project = |x: Vec3A| x / x.dot(center) - center;
+                    let radius = chunked
+                        .corners_of(coordinate)
+                        .iter()
+                        .map(|&x| project(x).length())
+                        .fold(0.0, f32::max);
+                    frame = (center, north, east, radius);
+                }
+
+                let (center, north, east, radius) = frame;
+                let offset = point / point.dot(center) - center;
+                Vec2::new(
+                    0.5 + offset.dot(east) / (2.0 * radius),
+                    0.5 - offset.dot(north) / (2.0 * radius),
+                )
+                .clamp(Vec2::ZERO, Vec2::ONE)
+            })
+            .collect();
+    }
+
+    /// Fills in [`GeometryData::tangents`] from the normals and UVs
+    /// in the same way as `MikkTSpace`, which is what normal maps are
+    /// usually baked against.
+    ///
+    /// Returns `false` without changing anything if there are no UVs.
+    #[cfg(feature = "mikktspace")]
+    pub fn generate_tangents(&mut self) -> bool {
+        if self.uvs.len() != self.points.len() {
+            return false;
+        }
+
+        let mut tangents = vec![Vec4::ZERO; self.points.len()];
+        let generated = bevy_mikktspace::generate_tangents(&mut Tangents {
+            geometry: self,
+            tangents: &mut tangents,
+        });
+
+        if generated {
+            self.tangents = tangents;
+        }
+        generated
+    }
+}
+
+/// What `MikkTSpace` needs to see of a [`GeometryData`].
+#[cfg(feature = "mikktspace")]
+struct Tangents<'a> {
+    geometry: &'a GeometryData,
+    tangents: &'a mut [Vec4],
+}
+
+#[cfg(feature = "mikktspace")]
+impl Tangents<'_> {
+    fn index(&self, face: usize, vert: usize) -> usize {
+        self.geometry.indices[face * 3 + vert] as usize
+    }
+}
+
+#[cfg(feature = "mikktspace")]
+impl bevy_mikktspace::Geometry for Tangents<'_> {
+    fn num_faces(&self) -> usize {
+        self.geometry.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.geometry.points[self.index(face, vert)].into()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.geometry.normals[self.index(face, vert)].into()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.geometry.uvs[self.index(face, vert)].into()
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let index = self.index(face, vert);
+        self.tangents[index] = tangent.into();
+    }
+}
+
+/// Builds the dual of the triangles around `points_to_process`.
+///
+/// # Panics
+/// If a vertex is missing; see [`try_dual`].
 pub fn dual<'a>(
     ico_points: &[Vec3A],
     points_to_process: impl Iterator<Item = (u32, Option<&'a Hexagonish<u32>>)>,
-    surrounding: &'a HashMap<u32, Hexagonish<u32>>,
-    mut make_translation: impl FnMut(u32, u32, Hexagonish<u32>),
+    surrounding: &'a impl Neighbours,
+    make_translation: impl FnMut(u32, u32, Hexagonish<u32>),
 ) -> GeometryData {
+    try_dual(ico_points, points_to_process, surrounding, make_translation)
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Like [`dual`], but reports vertices missing from
+/// `ico_points` or `surrounding` rather than panicking.
+///
+/// # Errors
+/// If a vertex is missing.
+#[instrument(skip_all, fields(tiles = tracing::field::Empty, points = tracing::field::Empty))]
+pub fn try_dual<'a>(
+    ico_points: &[Vec3A],
+    points_to_process: impl Iterator<Item = (u32, Option<&'a Hexagonish<u32>>)>,
+    surrounding: &'a impl Neighbours,
+    mut make_translation: impl FnMut(u32, u32, Hexagonish<u32>),
+) -> Result<GeometryData> {
     let mut points = Vec::new();
     let mut indices = Vec::new();
 
     let mut triangle_set = HashMap::<UnorderedTrio, u32>::new();
+    let mut tiles = 0;
 
     for (face, around) in points_to_process {
-        let around = around.unwrap_or_else(|| &surrounding[&face]);
+        tiles += 1;
+        let around = match around {
+            Some(around) => around.as_slice(),
+            None => surrounding
+                .neighbours(face)
+                .ok_or(Error::MissingVertex { vertex: face })?,
+        };
+        let position = |vertex: u32| {
+            ico_points
+                .get(vertex as usize)
+                .copied()
+                .ok_or(Error::MissingVertex { vertex })
+        };
         let mid = points.len();
 
         let mut mid_val = Vec3A::ZERO;
@@ -49,9 +291,9 @@ pub fn dual<'a>(
         for i in 0..around.len() {
             match triangle_set.entry(trio(face, around[i], around[(i + 1) % around.len()])) {
                 Entry::Vacant(x) => {
-                    let avg = ico_points[face as usize]
-                        + ico_points[around[i] as usize]
-                        + ico_points[around[(i + 1) % around.len()] as usize];
+                    let avg = position(face)?
+                        + position(around[i])?
+                        + position(around[(i + 1) % around.len()])?;
                     let avg = avg.normalize();
                     let idx = points.len();
                     points.push(avg);
@@ -91,10 +333,293 @@ pub fn dual<'a>(
         make_translation(face, mid as u32, edge_points);
     }
 
-    GeometryData {
+    tracing::Span::current()
+        .record("tiles", tiles)
+        .record("points", points.len());
+
+    Ok(GeometryData {
         normals: points.iter().map(|x| x.normalize()).collect(),
         points,
         indices,
+        uvs: Vec::new(),
+        tangents: Vec::new(),
+    })
+}
+
+/// Where [`dense_dual`] puts a triangle's corner: in the polygon of
+/// the lowest vertex around it, since that is processed first.
+#[derive(Copy, Clone)]
+struct Corner {
+    owner: u32,
+    /// Which triangle around `owner` this is.
+    at: usize,
+}
+
+/// The same as [`try_dual`] on every vertex of `surrounding` in
+/// increasing order, but with each tile worked out independently,
+/// so that they can be worked out on many threads if `parallel` is
+/// set and the `rayon` feature is on.
+///
+/// [`try_dual`] is still needed for subsets of the vertices in any
+/// order, which can't be laid out up front like this.
+///
+/// # Returns
+/// - The new `GeometryData`.
+/// - For each vertex, its center and corners in the new geometry,
+///   or `None` if it has no neighbours.
+///
+/// # Errors
+/// If a vertex is missing from `ico_points`, or the winding of
+/// `surrounding` is inconsistent.
+#[allow(clippy::type_complexity)]
+#[instrument(skip_all, fields(tiles = surrounding.vertex_count(), points = tracing::field::Empty))]
+pub(crate) fn dense_dual(
+    ico_points: &[Vec3A],
+    surrounding: &Adjacency,
+    parallel: bool,
+) -> Result<(GeometryData, Vec<Option<(u32, Hexagonish<u32>)>>)> {
+    let around = |face: u32| surrounding.neighbours(face).unwrap_or_default();
+    let triangle = |face: u32, at: usize| {
+        let around = around(face);
+        let mut trio = [face, around[at], around[(at + 1) % around.len()]];
+        trio.sort_unstable();
+        trio
+    };
+    let position = |vertex: u32| {
+        ico_points
+            .get(vertex as usize)
+            .copied()
+            .ok_or(Error::MissingVertex { vertex })
+    };
+
+    let corners = map_ids(surrounding.id_count(), parallel, |face| {
+        (0..around(face).len())
+            .map(|at| {
+                let trio = triangle(face, at);
+                let owner = trio[0];
+                let at = (0..around(owner).len())
+                    .find(|&x| triangle(owner, x) == trio)
+                    .ok_or(Error::InconsistentWinding { vertex: owner })?;
+                Ok(Corner { owner, at })
+            })
+            .collect::<Result<Hexagonish<_>>>()
+    })
+    .into_iter()
+    .collect::<Result<Vec<_>>>()?;
+
+    // Each tile adds its center, and then the corners it is first to see.
+    let owned = |face: u32, before: usize| {
+        corners[face as usize][..before]
+            .iter()
+            .filter(|x| x.owner == face)
+            .count() as u32
+    };
+    let starts = corners
+        .iter()
+        .enumerate()
+        .scan(0, |start, (face, list)| {
+            let this = *start;
+            if !list.is_empty() {
+                *start += 1 + owned(face as u32, list.len());
+            }
+            Some(this)
+        })
+        .collect::<Vec<_>>();
+    let index_of = |Corner { owner, at }: Corner| starts[owner as usize] + 1 + owned(owner, at);
+
+    let tiles = map_ids(surrounding.id_count(), parallel, |face| {
+        let list = &corners[face as usize];
+        if list.is_empty() {
+            return Ok(None);
+        }
+
+        let mut new_points = ArrayVec::<Vec3A, 7>::new();
+        let mut mid_val = Vec3A::ZERO;
+        new_points.push(Vec3A::ZERO);
+        for &corner in list {
+            let around = around(corner.owner);
+            let point = (position(corner.owner)?
+                + position(around[corner.at])?
+                + position(around[(corner.at + 1) % around.len()])?)
+            .normalize();
+            if corner.owner == face {
+                new_points.push(point);
+            }
+            mid_val += point;
+        }
+
+        mid_val /= list.len() as f32;
+        new_points[0] = mid_val;
+
+        let mid = starts[face as usize];
+        let edge_points = list.iter().map(|&x| index_of(x)).collect::<Hexagonish<_>>();
+
+        let mut indices = ArrayVec::<u32, 18>::new();
+        for i in 0..list.len() {
+            indices.extend([mid, edge_points[i], edge_points[(i + 1) % list.len()]]);
+        }
+
+        Ok(Some((new_points, indices, (mid, edge_points))))
+    })
+    .into_iter()
+    .collect::<Result<Vec<_>>>()?;
+
+    let mut geometry = GeometryData {
+        points: Vec::with_capacity(starts.last().map_or(0, |&x| x as usize + 7)),
+        normals: Vec::new(),
+        indices: Vec::with_capacity(tiles.len() * 18),
+        uvs: Vec::new(),
+        tangents: Vec::new(),
+    };
+    let translations = tiles
+        .into_iter()
+        .map(|tile| {
+            tile.map(|(points, indices, translation)| {
+                geometry.points.extend(points);
+                geometry.indices.extend(indices);
+                translation
+            })
+        })
+        .collect();
+
+    geometry.normals = map_ids(geometry.points.len(), parallel, |i| {
+        geometry.points[i as usize].normalize()
+    });
+    tracing::Span::current().record("points", geometry.points.len());
+
+    Ok((geometry, translations))
+}
+
+/// Maps `f` over `0..len`, on many threads if `parallel`
+/// is set and the `rayon` feature is on.
+#[cfg_attr(not(feature = "rayon"), allow(unused_variables))]
+fn map_ids<T: Send>(len: usize, parallel: bool, f: impl Fn(u32) -> T + Sync + Send) -> Vec<T> {
+    #[cfg(feature = "rayon")]
+    if parallel {
+        use rayon::prelude::*;
+        return (0..len as u32).into_par_iter().map(f).collect();
+    }
+
+    (0..len as u32).map(f).collect()
+}
+
+/// Builds the dual with every tile kept apart, for shading each one
+/// uniformly with hard edges between them.
+///
+/// Each tile gets its own center and corners, which aren't shared
+/// with its neighbours, laid out like [`dual`] with tiles in the order
+/// of [`Chunked::iter_all`]. Every point of a tile has the tile's normal,
+/// and `inset` in `0.0..1.0` pulls the corners that fraction of the way
+/// towards the center, leaving a gap between the tiles.
+///
+/// # Returns
+/// - The new `GeometryData`.
+/// - Which tile each point belongs to, as in [`Chunked::to_index`].
+#[instrument(skip_all, fields(tiles = chunked.tile_count()))]
+pub fn flat_dual(chunked: Chunked, inset: f32) -> (GeometryData, Vec<u32>) {
+    let mut geometry = GeometryData {
+        points: Vec::with_capacity(chunked.tile_count() * 7),
+        normals: Vec::with_capacity(chunked.tile_count() * 7),
+        indices: Vec::with_capacity(chunked.tile_count() * 18),
+        uvs: Vec::new(),
+        tangents: Vec::new(),
+    };
+    let mut tiles = Vec::with_capacity(chunked.tile_count() * 7);
+
+    for (tile, coordinate) in chunked.iter_all().enumerate() {
+        let corners = chunked.corners_of(coordinate);
+        push_polygon(&mut geometry, &corners, inset);
+        tiles.resize(geometry.points.len(), tile as u32);
+    }
+
+    (geometry, tiles)
+}
+
+/// Builds the tiles as prisms standing out from the unit sphere, for terrain.
+///
+/// Each tile's top is flat and `heights[tile]` above the unit sphere,
+/// laid out as in [`flat_dual`]. Walls are added only along the edges
+/// where a tile is higher than its neighbour, going down as far as the
+/// neighbour's top, so there are no hidden faces between tiles of the
+/// same height and nothing closes off the bottom.
+///
+/// # Returns
+/// - The new `GeometryData`.
+/// - Which tile each point belongs to, as in [`Chunked::to_index`].
+///   Each wall belongs to the higher tile.
+#[instrument(skip_all, fields(tiles = heights.tile_count(), walls = tracing::field::Empty))]
+pub fn prisms(heights: &Hexasphere<f32>) -> (GeometryData, Vec<u32>) {
+    let mut geometry = GeometryData {
+        points: Vec::with_capacity(heights.tile_count() * 7),
+        normals: Vec::with_capacity(heights.tile_count() * 7),
+        indices: Vec::with_capacity(heights.tile_count() * 18),
+        uvs: Vec::new(),
+        tangents: Vec::new(),
+    };
+    let mut tiles = Vec::with_capacity(heights.tile_count() * 7);
+    let mut walls = 0;
+
+    for (tile, coordinate) in heights.iter_all().enumerate() {
+        let height = heights[coordinate];
+        let corners = heights.corners_of(coordinate);
+        let top = corners
+            .iter()
+            .map(|&x| x * (1.0 + height))
+            .collect::<Hexagonish<_>>();
+        push_polygon(&mut geometry, &top, 0.0);
+
+        // The edge towards neighbour `i` is between corners `i - 1` and `i`.
+        let around = heights.surrounding(coordinate);
+        for (i, &neighbour) in around.iter().enumerate() {
+            let below = heights[neighbour];
+            if below >= height {
+                continue;
+            }
+
+            let (a, b) = (corners[(i + corners.len() - 1) % corners.len()], corners[i]);
+            let normal = (b - a).cross(a + b).normalize();
+            let start = geometry.points.len() as u32;
+            geometry.points.extend([
+                a * (1.0 + below),
+                b * (1.0 + below),
+                b * (1.0 + height),
+                a * (1.0 + height),
+            ]);
+            geometry.normals.extend([normal; 4]);
+            geometry
+                .indices
+                .extend([0, 1, 2, 0, 2, 3].map(|x| start + x));
+            walls += 1;
+        }
+
+        tiles.resize(geometry.points.len(), tile as u32);
+    }
+
+    tracing::Span::current().record("walls", walls);
+
+    (geometry, tiles)
+}
+
+/// Adds a polygon as a fan around the average of its corners, all facing
+/// the direction of that average, with the corners `inset` of the way in.
+fn push_polygon(geometry: &mut GeometryData, corners: &[Vec3A], inset: f32) {
+    let center = corners.iter().sum::<Vec3A>() / corners.len() as f32;
+    let normal = center.normalize();
+
+    let mid = geometry.points.len() as u32;
+    geometry.points.push(center);
+    geometry
+        .points
+        .extend(corners.iter().map(|&x| center.lerp(x, 1.0 - inset)));
+    geometry
+        .normals
+        .extend(std::iter::repeat(normal).take(corners.len() + 1));
+
+    let n = corners.len() as u32;
+    for i in 0..n {
+        geometry
+            .indices
+            .extend([mid, mid + 1 + i, mid + 1 + (i + 1) % n]);
     }
 }
 
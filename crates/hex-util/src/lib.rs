@@ -1,30 +1,53 @@
 //! Example usage: https://play.rust-lang.org/?version=stable&mode=debug&edition=2021&gist=6ee9f10224131656ea652f52718df8cf
 
 use arrayvec::ArrayVec;
-use bevy::{ecs::system::Resource, utils::Instant};
+#[cfg(feature = "bevy")]
+use bevy::ecs::system::Resource;
 use glam::Vec3A;
-use std::cmp::Ordering;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt::{Debug, Formatter};
 use std::ops::{Deref, Index, IndexMut};
-
+use std::sync::Arc;
+use tracing::instrument;
+
+pub mod adjacency;
+pub mod edges;
+pub mod error;
+pub mod export;
+pub mod file;
 pub mod geometry_util;
+mod net;
+pub mod regions;
 
+use adjacency::{Adjacency, NeighbourTable, Neighbours};
+pub use edges::{Corner, Edge};
+pub use error::{Error, Result};
 use geometry_util::GeometryData;
 
 #[cfg(feature = "algorithms")]
 pub mod algorithms;
 
+#[cfg(feature = "rayon")]
+mod parallel;
+
 /// Either 5 or 6 elements.
 pub type Hexagonish<T> = ArrayVec<T, 6>;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Chunked {
     subdivisions: usize,
 }
 
 impl Chunked {
+    pub fn new(subdivisions: usize) -> Self {
+        Self { subdivisions }
+    }
+
     /// Yields the coordinates surrounding a given coordinate.
     pub fn surrounding(self, x: Coordinate) -> Hexagonish<Coordinate> {
         match x {
@@ -241,125 +264,396 @@ impl Chunked {
         }
     }
 
+    /// Errors if `coord` isn't on this sphere.
+    fn check(self, coord: Coordinate) -> Result<()> {
+        if self.is_valid(coord) {
+            Ok(())
+        } else {
+            Err(Error::InvalidCoordinate {
+                coordinate: coord,
+                subdivisions: self.subdivisions,
+            })
+        }
+    }
+
     pub fn subdivisions(self) -> usize {
         self.subdivisions
     }
 
-    /// Parameters:
-    /// - `from` and `to` must be adjacent.
-    /// - `choose` takes in:
-    ///    1) A coordinate for a hexagon
-    ///    2) A coordinate for an adjacent pentagon
-    ///    3) Two possible directions to go in
+    /// The number of tiles on the sphere.
+    pub fn tile_count(self) -> usize {
+        2 + 5 * self.chunk_len()
+    }
+
+    /// Number of tiles in each of the five chunks.
+    fn chunk_len(self) -> usize {
+        2 * (self.subdivisions + 1) * (self.subdivisions + 1)
+    }
+
+    /// Turns a coordinate into a dense index in `0..tile_count()`.
     ///
-    ///    And returns which of the two directions to go.
-    pub fn continue_line<F>(self, from: Coordinate, to: Coordinate, choose: F) -> LineCont<F>
-    where
-        F: FnMut(Coordinate, Coordinate, (Coordinate, Coordinate)) -> Coordinate,
-    {
-        LineCont {
-            sphere: self,
-            prev: from,
-            next: to,
-            choose,
+    /// Indices follow the order of [`Chunked::iter_all`], which is
+    /// also the order [`Hexasphere::all`] stores its data in.
+    ///
+    /// # Panics
+    /// In debug builds, if `coordinate` isn't on this sphere, since it
+    /// would otherwise alias some other tile's index. Also if there are
+    /// too many tiles for a `u32`.
+    pub fn to_index(self, coordinate: Coordinate) -> u32 {
+        debug_assert!(
+            self.is_valid(coordinate),
+            "{coordinate:?} isn't on a sphere with {} subdivisions!",
+            self.subdivisions
+        );
+
+        let index = match coordinate {
+            Coordinate::Top => 0,
+            Coordinate::Bottom => 1,
+            Coordinate::Inside { chunk, short, long } => {
+                2 + chunk as usize * self.chunk_len() + short * 2 * (self.subdivisions + 1) + long
+            }
+        };
+
+        u32::try_from(index).expect("Too many tiles to index!")
+    }
+
+    /// The inverse of [`Chunked::to_index`].
+    ///
+    /// # Panics
+    /// If `index` is not below [`Chunked::tile_count`].
+    pub fn from_index(self, index: u32) -> Coordinate {
+        let index = index as usize;
+        assert!(index < self.tile_count(), "Tile index is invalid!");
+
+        match index {
+            0 => Coordinate::Top,
+            1 => Coordinate::Bottom,
+            _ => {
+                let index = index - 2;
+                let in_chunk = index % self.chunk_len();
+                coord(
+                    (index / self.chunk_len()) as u8,
+                    in_chunk / (2 * (self.subdivisions + 1)),
+                    in_chunk % (2 * (self.subdivisions + 1)),
+                )
+            }
         }
     }
 
-    pub fn find_blobs(self, coords: impl Iterator<Item = Coordinate>) -> Option<Vec<Blob>> {
-        let mut to_explore = vec![];
-        let mut all = coords.collect::<HashSet<_>>();
+    /// The exact number of steps between two coordinates.
+    ///
+    /// Unlike [`geometry_util::steps_between`], this works purely
+    /// from the coordinates, so it is exact across chunk seams and
+    /// around the pentagons.
+    pub fn distance(self, a: Coordinate, b: Coordinate) -> usize {
+        if a == b {
+            return 0;
+        }
 
-        let mut yielded = Vec::new();
+        net::distance(self.subdivisions, a, b)
+    }
 
-        while !all.is_empty() {
-            let first = *all.iter().next().unwrap();
+    /// The position of a tile's center on the unit sphere.
+    ///
+    /// This is computed from the coordinate alone, laying the chunks
+    /// out on an icosahedron oriented like the `hexasphere` crate's.
+    pub fn center_of(self, coordinate: Coordinate) -> Vec3A {
+        let n = self.subdivisions as i64 + 1;
+        let (chunk, [short, long]) = net::locate(n, coordinate);
+        net::to_sphere(n, chunk.unwrap_or(0), [short as f32, long as f32])
+    }
 
-            to_explore.push(first);
+    /// The tile whose center is nearest to where `direction` points.
+    ///
+    /// Rather than searching the sphere, this goes straight to the
+    /// icosahedron face under `direction`, so it takes about the same
+    /// time however many subdivisions there are.
+    pub fn coordinate_at(self, direction: Vec3A) -> Coordinate {
+        let direction = direction.normalize();
+        let start = net::from_sphere(self.subdivisions as i64 + 1, direction);
+        self.climb(start, direction)
+    }
 
-            let mut current_contents = HashSet::new();
-            current_contents.insert(first);
+    /// Steps from `current` to whichever neighbour is nearest `direction`
+    /// until none are nearer.
+    fn climb(self, mut current: Coordinate, direction: Vec3A) -> Coordinate {
+        let mut best = direction.dot(self.center_of(current));
 
-            let mut bordered = Vec::new();
+        loop {
+            let nearest = self
+                .surrounding(current)
+                .into_iter()
+                .map(|x| (direction.dot(self.center_of(x)), x))
+                .filter(|&(dot, _)| dot > best)
+                .max_by(|a, b| a.0.total_cmp(&b.0).then(b.1.cmp(&a.1)));
 
-            while let Some(next) = to_explore.pop() {
-                let mut bordered_by_nothing = false;
+            let Some((dot, next)) = nearest else {
+                return current;
+            };
 
-                for around in self.surrounding(next) {
-                    let exists = all.contains(&around);
+            best = dot;
+            current = next;
+        }
+    }
 
-                    if exists && !current_contents.contains(&around) {
-                        to_explore.push(around);
-                        current_contents.insert(around);
-                    }
+    /// The tile at a latitude and longitude, in radians.
+    ///
+    /// `Top` is at the north pole, and longitude `0` points along `+x`,
+    /// increasing eastwards in the winding of [`Chunked::surrounding`].
+    pub fn coordinate_at_latlon(self, lat: f32, lon: f32) -> Coordinate {
+        let (sin_lat, cos_lat) = lat.sin_cos();
+        let (sin_lon, cos_lon) = lon.sin_cos();
+        self.coordinate_at(Vec3A::new(cos_lat * cos_lon, sin_lat, -cos_lat * sin_lon))
+    }
 
-                    bordered_by_nothing |= !exists;
-                }
+    /// The corners of a tile's polygon on the unit sphere.
+    ///
+    /// Corner `i` lies between neighbours `i` and `i + 1` as yielded
+    /// by [`Chunked::surrounding`], the same as the dual geometry.
+    pub fn corners_of(self, coordinate: Coordinate) -> Hexagonish<Vec3A> {
+        let center = self.center_of(coordinate);
+        let around = self
+            .surrounding(coordinate)
+            .into_iter()
+            .map(|x| self.center_of(x))
+            .collect::<Hexagonish<_>>();
 
-                if bordered_by_nothing {
-                    bordered.push(next);
-                }
-            }
+        (0..around.len())
+            .map(|i| (center + around[i] + around[(i + 1) % around.len()]).normalize())
+            .collect()
+    }
+
+    /// Yields the coordinates exactly `radius` steps away from `center`.
+    ///
+    /// They are yielded walking around `center` in the same winding
+    /// as [`Chunked::surrounding`], so a radius of 1 yields exactly
+    /// `surrounding(center)`.
+    pub fn ring(self, center: Coordinate, radius: usize) -> impl Iterator<Item = Coordinate> {
+        let distances = self.distances_within(center, radius);
+        self.trace_ring(center, radius, &distances).into_iter()
+    }
 
-            all.retain(|x| !current_contents.contains(x));
+    /// Yields the coordinates at most `radius` steps away from `center`.
+    ///
+    /// Starts with `center` and then yields each [`Chunked::ring`]
+    /// in order of increasing radius.
+    pub fn spiral(self, center: Coordinate, radius: usize) -> impl Iterator<Item = Coordinate> {
+        let distances = self.distances_within(center, radius);
+        (0..=radius)
+            .flat_map(|r| self.trace_ring(center, r, &distances))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
 
-            yielded.push(Blob {
-                contents: current_contents,
-                borders: bordered,
+    /// Yields the tiles along the great circle arc from `from` to `to`,
+    /// starting with `from` and ending with `to`.
+    ///
+    /// This is always a shortest path, so it has exactly
+    /// [`Chunked::distance`]` + 1` tiles, each adjacent to the one before.
+    /// Of the shortest paths, it takes one hugging the arc, and the
+    /// line from `to` back to `from` is exactly this one reversed. When
+    /// the two are on opposite sides of the sphere, which of the many
+    /// arcs is followed is arbitrary but always the same.
+    pub fn line(self, from: Coordinate, to: Coordinate) -> impl Iterator<Item = Coordinate> {
+        // Always walk the same way, so either direction takes the same tiles.
+        let (start, end) = if from <= to { (from, to) } else { (to, from) };
+        let (a, b) = (self.center_of(start), self.center_of(end));
+        let axis = a
+            .cross(b)
+            .try_normalize()
+            .unwrap_or_else(|| a.any_orthonormal_vector());
+
+        let mut remaining = self.distance(start, end);
+        let mut line = Vec::with_capacity(remaining + 1);
+        let mut current = start;
+        line.push(current);
+
+        while remaining > 0 {
+            remaining -= 1;
+            // Of the neighbours a step closer to `end`, take the one nearest the arc. Trying
+            // those heading towards `end` first means the distance is rarely needed twice.
+            let here = self.center_of(current).dot(b);
+            let mut around = self
+                .surrounding(current)
+                .into_iter()
+                .map(|x| {
+                    let center = self.center_of(x);
+                    (center.dot(b) <= here, center.dot(axis).abs(), x)
+                })
+                .collect::<Hexagonish<_>>();
+            around.sort_unstable_by(|x, y| {
+                (x.0.cmp(&y.0))
+                    .then(x.1.total_cmp(&y.1))
+                    .then(x.2.cmp(&y.2))
             });
+            current = around
+                .into_iter()
+                .map(|(_, _, x)| x)
+                .find(|&x| self.distance(x, end) == remaining)
+                .expect("Some neighbour is always a step closer.");
+            line.push(current);
         }
 
-        Some(yielded)
+        if from > to {
+            line.reverse();
+        }
+
+        line.into_iter()
     }
 
-    pub fn blob_borders(self, blob: &Blob) -> Option<Vec<Vec<Coordinate>>> {
-        self.find_blobs(blob.borders.iter().copied())
-            .map(|x| x.into_iter().map(|blob| blob.borders).collect::<Vec<_>>())
+    /// Floods out from `center`, recording the distance to everything
+    /// at most `radius` steps away.
+    fn distances_within(self, center: Coordinate, radius: usize) -> HashMap<Coordinate, usize> {
+        let mut distances = HashMap::from([(center, 0)]);
+        let mut frontier = vec![center];
+
+        for step in 1..=radius {
+            let mut next = Vec::new();
+            for x in frontier {
+                for around in self.surrounding(x) {
+                    if let Entry::Vacant(entry) = distances.entry(around) {
+                        entry.insert(step);
+                        next.push(around);
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        distances
     }
 
-    pub fn ring_order(self, coordinates: &mut [Coordinate], inside: &HashSet<Coordinate>) {
-        let mut visited = HashSet::new();
-        let all = coordinates.iter().copied().collect::<HashSet<_>>();
-        let mut ordered = Vec::new();
+    fn trace_ring(
+        self,
+        center: Coordinate,
+        radius: usize,
+        distances: &HashMap<Coordinate, usize>,
+    ) -> Vec<Coordinate> {
+        if radius == 0 {
+            return vec![center];
+        }
 
-        let mut current = coordinates[0];
+        let at = |x: &Coordinate, d: usize| distances.get(x) == Some(&d);
+        let inside = |x: &Coordinate| distances.get(x).is_some_and(|&d| d < radius);
+
+        // Ordered, so falling back to the first tile left is deterministic.
+        let mut remaining = distances
+            .iter()
+            .filter(|&(_, &d)| d == radius)
+            .map(|(&x, _)| x)
+            .collect::<BTreeSet<_>>();
+
+        // Head straight out from the first neighbour to pick a start
+        // which doesn't depend on hashing order.
+        let mut prev = center;
+        let mut start = Some(self.surrounding(center)[0]);
+        for step in 2..=radius {
+            let Some(current) = start else {
+                break;
+            };
 
-        visited.insert(current);
+            let around = self.surrounding(current);
+            let straight = rotate(prev, 3, &around);
+            prev = current;
+            start = if at(&straight, step) {
+                Some(straight)
+            } else {
+                around.into_iter().find(|x| at(x, step))
+            };
+        }
 
-        ordered.push(current);
+        let mut ring = Vec::with_capacity(remaining.len());
+        let mut next = start.or_else(|| remaining.first().copied());
+
+        while let Some(current) = next {
+            ring.push(current);
+            remaining.remove(&current);
+
+            // Going around `center`, the next tile on the ring is the one
+            // just before the run of tiles closer in.
+            let around = self.surrounding(current);
+            let unvisited = |x: &Coordinate| at(x, radius) && remaining.contains(x);
+            next = (0..around.len())
+                .find(|&i| unvisited(&around[i]) && inside(&around[(i + 1) % around.len()]))
+                .map(|i| around[i])
+                .or_else(|| around.iter().copied().find(unvisited))
+                .or_else(|| remaining.first().copied());
+        }
 
-        while visited.len() != all.len() {
-            let next = self
-                .surrounding(current)
-                .into_iter()
-                .filter(|x| all.contains(x))
-                .find(|x| !visited.contains(x))
-                .unwrap();
+        ring
+    }
 
-            visited.insert(next);
-            ordered.push(next);
-            current = next;
+    /// Parameters:
+    /// - `from` and `to` must be adjacent.
+    /// - `choose` takes in:
+    ///    1) A coordinate for a hexagon
+    ///    2) A coordinate for an adjacent pentagon
+    ///    3) Two possible directions to go in
+    ///
+    ///    And returns which of the two directions to go.
+    pub fn continue_line<F>(self, from: Coordinate, to: Coordinate, choose: F) -> LineCont<F>
+    where
+        F: FnMut(Coordinate, Coordinate, (Coordinate, Coordinate)) -> Coordinate,
+    {
+        LineCont {
+            sphere: self,
+            prev: from,
+            next: to,
+            choose,
         }
+    }
+
+    /// Like [`Chunked::continue_line`], but checks that `from` and `to` are adjacent.
+    ///
+    /// # Errors
+    /// If either coordinate is invalid, or they aren't adjacent.
+    pub fn try_continue_line<F>(
+        self,
+        from: Coordinate,
+        to: Coordinate,
+        choose: F,
+    ) -> Result<LineCont<F>>
+    where
+        F: FnMut(Coordinate, Coordinate, (Coordinate, Coordinate)) -> Coordinate,
+    {
+        self.check(from)?;
+        self.check(to)?;
 
-        coordinates.copy_from_slice(&ordered);
+        if self.surrounding(to).contains(&from) {
+            Ok(self.continue_line(from, to, choose))
+        } else {
+            Err(Error::NotAdjacent { from, to })
+        }
+    }
 
-        // Test for correct chirality.
-        let first = coordinates[0];
-        let second = coordinates[1];
+    pub fn find_blobs(self, coords: impl Iterator<Item = Coordinate>) -> Option<Vec<Blob>> {
+        find_blobs(|x| self.surrounding(x), coords)
+    }
 
-        let first_surrounding = self.surrounding(first);
-        let common_out = self
-            .surrounding(second)
-            .into_iter()
-            .find(|x| first_surrounding.contains(x) && !inside.contains(x))
-            .unwrap();
+    pub fn blob_borders(self, blob: &Blob) -> Option<Vec<Vec<Coordinate>>> {
+        self.find_blobs(blob.borders.iter().copied())
+            .map(|x| x.into_iter().map(|blob| blob.borders).collect::<Vec<_>>())
+    }
 
-        // Since it's outside, the first two should have reversed winding for it.
-        let out_surrounding = self.surrounding(common_out);
+    /// # Panics
+    /// If `coordinates` isn't a ring; see [`Chunked::try_ring_order`].
+    pub fn ring_order(self, coordinates: &mut [Coordinate], inside: &HashSet<Coordinate>) {
+        self.try_ring_order(coordinates, inside)
+            .unwrap_or_else(|e| panic!("{e}"));
+    }
 
-        let idx = out_surrounding.iter().position(|x| *x == second).unwrap();
-        if first != out_surrounding[(idx + 1) % out_surrounding.len()] {
-            coordinates.reverse();
-        }
+    /// Orders `coordinates` to walk around the ring
+    /// they form around `inside` in the winding order.
+    ///
+    /// # Errors
+    /// If `coordinates` can't be walked in a single ring,
+    /// in which case they are left as they were.
+    pub fn try_ring_order(
+        self,
+        coordinates: &mut [Coordinate],
+        inside: &HashSet<Coordinate>,
+    ) -> Result<()> {
+        ring_order(|x| self.surrounding(x), coordinates, inside)
     }
 
     pub fn iter_all(self) -> impl Iterator<Item = Coordinate> {
@@ -382,12 +676,79 @@ impl Chunked {
 /// This does not deal with geometry at all,
 /// however the algorithms in its impl allow you
 /// to tie that into its creation.
-#[derive(Clone, Debug, PartialEq, Resource)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "bevy", derive(Resource))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "Unchecked<T>")
+)]
+// Loading goes through `Unchecked`, so the chunk lengths are still checked.
+#[cfg_attr(feature = "serde", allow(clippy::unsafe_derive_deserialize))]
 pub struct Hexasphere<T> {
     inner: Chunked,
     top: T,
     bottom: T,
     chunks: [Vec<T>; 5],
+    /// Only a cache, so it isn't saved or compared.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    neighbours: Option<Arc<NeighbourTable>>,
+}
+
+impl<T: PartialEq> PartialEq for Hexasphere<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+            && self.top == other.top
+            && self.bottom == other.bottom
+            && self.chunks == other.chunks
+    }
+}
+
+/// A [`Hexasphere`] as it comes out of a file, before
+/// its chunks have been checked against its subdivisions.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct Unchecked<T> {
+    inner: Chunked,
+    top: T,
+    bottom: T,
+    chunks: [Vec<T>; 5],
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<Unchecked<T>> for Hexasphere<T> {
+    type Error = Error;
+
+    fn try_from(value: Unchecked<T>) -> Result<Self> {
+        let Unchecked {
+            inner,
+            top,
+            bottom,
+            chunks,
+        } = value;
+
+        // Too many subdivisions saturates, so no chunk can match.
+        let n = inner.subdivisions.saturating_add(1);
+        let expected = n.saturating_mul(n).saturating_mul(2);
+
+        for (chunk, contents) in chunks.iter().enumerate() {
+            if contents.len() != expected {
+                return Err(Error::ChunkLength {
+                    chunk,
+                    len: contents.len(),
+                    expected,
+                });
+            }
+        }
+
+        Ok(Self {
+            inner,
+            top,
+            bottom,
+            chunks,
+            neighbours: None,
+        })
+    }
 }
 
 impl<T> Deref for Hexasphere<T> {
@@ -399,6 +760,31 @@ impl<T> Deref for Hexasphere<T> {
 }
 
 impl<T> Hexasphere<T> {
+    /// Creates the organization structure without any geometry,
+    /// with `make` providing the entry for each coordinate.
+    pub fn from_subdivisions(subdivisions: usize, mut make: impl FnMut(Coordinate) -> T) -> Self {
+        let inner = Chunked { subdivisions };
+        let top = make(Coordinate::Top);
+        let bottom = make(Coordinate::Bottom);
+        let chunks = [0, 1, 2, 3, 4].map(|chunk| {
+            (0..inner.chunk_len())
+                .map(|i| {
+                    let short = i / (2 * (subdivisions + 1));
+                    let long = i % (2 * (subdivisions + 1));
+                    make(coord(chunk, short, long))
+                })
+                .collect::<Vec<_>>()
+        });
+
+        Self {
+            inner,
+            top,
+            bottom,
+            chunks,
+            neighbours: None,
+        }
+    }
+
     /// Assumes that the geometry is from the hexasphere
     /// geometry crate to organize itself.
     ///
@@ -413,18 +799,35 @@ impl<T> Hexasphere<T> {
     /// # Returns
     /// - The organizational structure, `Self`.
     /// - The adjacency map.
+    ///
+    /// # Panics
+    /// If `indices` is malformed; see [`Hexasphere::try_from_hexasphere_geometry`].
     pub fn from_hexasphere_geometry(
         subdivisions: usize,
         indices: &[u32],
         make: impl FnMut(u32, Coordinate) -> T,
     ) -> (Self, HashMap<u32, Hexagonish<u32>>) {
+        Self::try_from_hexasphere_geometry(subdivisions, indices, make)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`Hexasphere::from_hexasphere_geometry`], but reports malformed geometry.
+    ///
+    /// # Errors
+    /// If `indices` aren't triangles consistently wound around
+    /// a sphere with `subdivisions` subdivisions.
+    pub fn try_from_hexasphere_geometry(
+        subdivisions: usize,
+        indices: &[u32],
+        make: impl FnMut(u32, Coordinate) -> T,
+    ) -> Result<(Self, HashMap<u32, Hexagonish<u32>>)> {
         let mut coordinate_store = HashMap::new();
-        make_coordinate_store(indices, &mut coordinate_store);
+        try_make_coordinate_store(indices, &mut coordinate_store)?;
 
-        (
-            Self::make_from_surrounding(subdivisions, &coordinate_store, make),
+        Ok((
+            Self::try_make_from_surrounding(subdivisions, &coordinate_store, make)?,
             coordinate_store,
-        )
+        ))
     }
 
     /// Creates the organization structure, `Hexasphere`.
@@ -436,52 +839,73 @@ impl<T> Hexasphere<T> {
     ///   given index, the yielded list preserves the winding order.
     /// - `make` allows the user to translate from an old index into
     ///   a `Coordinate`, as well as providing an entry for that coordinate.
+    ///
+    /// # Panics
+    /// If `coordinate_store` is malformed; see [`Hexasphere::try_make_from_surrounding`].
     pub fn make_from_surrounding(
         subdivisions: usize,
-        coordinate_store: &HashMap<u32, Hexagonish<u32>>,
-        mut make: impl FnMut(u32, Coordinate) -> T,
+        coordinate_store: &impl Neighbours,
+        make: impl FnMut(u32, Coordinate) -> T,
     ) -> Self {
-        let top = make(0, Coordinate::Top);
-        let bottom = make(11, Coordinate::Bottom);
+        Self::try_make_from_surrounding(subdivisions, coordinate_store, make)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
 
-        let mut chunks = [Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new()];
+    /// Like [`Hexasphere::make_from_surrounding`], but reports a malformed adjacency map.
+    ///
+    /// # Errors
+    /// If `coordinate_store` is missing vertices, or its winding doesn't
+    /// walk out a sphere with `subdivisions` subdivisions. `make` is never
+    /// called twice for the same old index, nor before the poles are
+    /// known to be there.
+    pub fn try_make_from_surrounding(
+        subdivisions: usize,
+        coordinate_store: &impl Neighbours,
+        mut make: impl FnMut(u32, Coordinate) -> T,
+    ) -> Result<Self> {
+        Self::try_make_from_surrounding_with(subdivisions, coordinate_store, |old, coord| {
+            Ok(make(old, coord))
+        })
+    }
 
-        let rotate_by = |previous: u32, this: u32, by: isize| {
-            let list = coordinate_store.get(&this).unwrap();
-            let idx = list.iter().position(|&x| x == previous).unwrap();
-            list[(idx as isize + by).rem_euclid(list.len() as isize) as usize]
+    /// [`Hexasphere::try_make_from_surrounding`], but `make` can fail too.
+    #[instrument(
+        name = "try_make_from_surrounding",
+        skip_all,
+        fields(subdivisions = subdivisions, tiles = coordinate_store.vertex_count())
+    )]
+    fn try_make_from_surrounding_with(
+        subdivisions: usize,
+        coordinate_store: &impl Neighbours,
+        mut make: impl FnMut(u32, Coordinate) -> Result<T>,
+    ) -> Result<Self> {
+        check_poles(coordinate_store)?;
+
+        let mut seen = HashSet::with_capacity(coordinate_store.vertex_count());
+        let mut make = |old: u32, coord: Coordinate| {
+            if seen.insert(old) {
+                make(old, coord)
+            } else {
+                Err(Error::InconsistentWinding { vertex: old })
+            }
         };
 
-        for (chunk, data) in chunks.iter_mut().enumerate() {
-            let mut short_prev = 0;
-            let mut short_root = coordinate_store.get(&0).unwrap()[chunk];
-
-            for short in 0..subdivisions + 1 {
-                data.push(make(short_root, coord(chunk as u8, short, 0)));
-
-                let mut long_root = rotate_by(short_prev, short_root, -2);
-                let mut long_prev = short_root;
+        let top = make(0, Coordinate::Top)?;
+        let bottom = make(11, Coordinate::Bottom)?;
 
-                for long in 1..2 * (subdivisions + 1) {
-                    data.push(make(long_root, coord(chunk as u8, short, long)));
-
-                    let new_long_root = rotate_by(long_prev, long_root, -3);
-                    long_prev = long_root;
-                    long_root = new_long_root;
-                }
+        let mut chunks = [Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new()];
 
-                let new_short_root = rotate_by(short_prev, short_root, -3);
-                short_prev = short_root;
-                short_root = new_short_root;
-            }
+        for (chunk, data) in chunks.iter_mut().enumerate() {
+            *data = walk_chunk(subdivisions, coordinate_store, chunk as u8, &mut make)?;
         }
 
-        Self {
+        Ok(Self {
             inner: Chunked { subdivisions },
             top,
             bottom,
             chunks,
-        }
+            neighbours: None,
+        })
     }
 
     /// Generates duals of geometry given indices and points.
@@ -504,43 +928,99 @@ impl<T> Hexasphere<T> {
     /// - The organization structure `Hexasphere`.
     /// - The new `GeometryData`.
     /// - The temporary data.
+    ///
+    /// # Panics
+    /// If the geometry is malformed; see [`Hexasphere::try_make_and_dual`].
     pub fn make_and_dual<E>(
         subdivisions: usize,
         indices: &[u32],
         ico_points: &[Vec3A],
         make_temporary: impl FnOnce(&GeometryData) -> E,
-        mut make: impl FnMut(u32, Hexagonish<u32>, Coordinate, &mut GeometryData, &mut E) -> T,
+        make: impl FnMut(u32, Hexagonish<u32>, Coordinate, &mut GeometryData, &mut E) -> T,
     ) -> (Self, GeometryData, E) {
-        let start = Instant::now();
-        let mut coordinate_store = HashMap::new();
-        make_coordinate_store(indices, &mut coordinate_store);
-        println!("[{}:{}] CStore: {:?}", file!(), line!(), start.elapsed());
-
-        let start = Instant::now();
-        let mut convert_to_dual_space = HashMap::new();
+        Self::try_make_and_dual(subdivisions, indices, ico_points, make_temporary, make)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
 
-        let mut dual_data = geometry_util::dual(
-            ico_points,
-            coordinate_store
-                .iter()
-                .map(|(&x, around)| (x, Some(around))),
-            &coordinate_store,
-            |old, new, edges| {
-                convert_to_dual_space.insert(old, (new, edges));
-            },
-        );
-        println!("[{}:{}] Dual: {:?}", file!(), line!(), start.elapsed());
+    /// Like [`Hexasphere::make_and_dual`], but reports malformed geometry.
+    ///
+    /// # Errors
+    /// If `indices` refers to points not in `ico_points`, or otherwise
+    /// fails as in [`Hexasphere::try_from_hexasphere_geometry`].
+    #[instrument(skip_all, fields(subdivisions = subdivisions, tiles = ico_points.len()))]
+    pub fn try_make_and_dual<E>(
+        subdivisions: usize,
+        indices: &[u32],
+        ico_points: &[Vec3A],
+        make_temporary: impl FnOnce(&GeometryData) -> E,
+        mut make: impl FnMut(u32, Hexagonish<u32>, Coordinate, &mut GeometryData, &mut E) -> T,
+    ) -> Result<(Self, GeometryData, E)> {
+        let coordinate_store = Adjacency::from_indices(indices)?;
+        let (mut dual_data, mut convert_to_dual_space) =
+            geometry_util::dense_dual(ico_points, &coordinate_store, false)?;
 
-        let start = Instant::now();
         let mut temp = make_temporary(&dual_data);
 
         let surrounding =
-            Self::make_from_surrounding(subdivisions, &coordinate_store, |old, coord| {
-                let (new, edges) = convert_to_dual_space.get(&old).unwrap().clone();
+            Self::try_make_from_surrounding_with(subdivisions, &coordinate_store, |old, coord| {
+                let (new, edges) = convert_to_dual_space
+                    .get_mut(old as usize)
+                    .and_then(Option::take)
+                    .ok_or(Error::MissingVertex { vertex: old })?;
 
-                make(new, edges, coord, &mut dual_data, &mut temp)
-            });
-        println!("[{}:{}] Make: {:?}", file!(), line!(), start.elapsed());
+                Ok(make(new, edges, coord, &mut dual_data, &mut temp))
+            })?;
+
+        Ok((surrounding, dual_data, temp))
+    }
+
+    /// Generates the dual geometry and organization structure
+    /// from scratch, without needing an `IcoSphere` from the
+    /// `hexasphere` crate.
+    ///
+    /// Tile centers come from [`Chunked::center_of`], so this
+    /// doesn't depend on any external vertex ordering.
+    ///
+    /// # Arguments
+    /// - `subdivisions`: Number of subdivisions.
+    /// - `make_temporary` and `make`: As in [`Hexasphere::make_and_dual`].
+    ///
+    /// # Returns:
+    /// - The organization structure `Hexasphere`.
+    /// - The new `GeometryData`.
+    /// - The temporary data.
+    #[instrument(skip_all, fields(subdivisions = subdivisions, tiles = Chunked { subdivisions }.tile_count()))]
+    pub fn make_dual<E>(
+        subdivisions: usize,
+        make_temporary: impl FnOnce(&GeometryData) -> E,
+        mut make: impl FnMut(u32, Hexagonish<u32>, Coordinate, &mut GeometryData, &mut E) -> T,
+    ) -> (Self, GeometryData, E) {
+        let chunked = Chunked { subdivisions };
+        let centers = chunked
+            .iter_all()
+            .map(|x| chunked.center_of(x))
+            .collect::<Vec<_>>();
+        // `iter_all` goes in index order.
+        let coordinate_store = Adjacency::from_lists(chunked.iter_all().map(|x| {
+            let around = chunked.surrounding(x).into_iter();
+            around
+                .map(|y| chunked.to_index(y))
+                .collect::<Hexagonish<_>>()
+        }));
+
+        let (mut dual_data, mut convert_to_dual_space) =
+            geometry_util::dense_dual(&centers, &coordinate_store, false)
+                .unwrap_or_else(|e| unreachable!("{e}"));
+
+        let mut temp = make_temporary(&dual_data);
+
+        let surrounding = Self::from_subdivisions(subdivisions, |coord| {
+            let (new, edges) = convert_to_dual_space[chunked.to_index(coord) as usize]
+                .take()
+                .unwrap_or_else(|| unreachable!());
+
+            make(new, edges, coord, &mut dual_data, &mut temp)
+        });
 
         (surrounding, dual_data, temp)
     }
@@ -558,7 +1038,31 @@ impl<T> Hexasphere<T> {
     ///   and the indices of the vertices of the polygon in each buffer, (the `Hexagonish<u32>`).
     ///   Afterwards, it takes the coordinate to be processed, the new geometry data, and the
     ///   temporary data.
+    ///
+    /// # Panics
+    /// If the geometry is malformed; see [`Hexasphere::try_chunked_dual`].
     pub fn chunked_dual<E>(
+        subdivisions: usize,
+        next_indices: impl FnMut(&mut Vec<u32>),
+        ico_points: &[Vec3A],
+        make_temporary: impl FnOnce(&[GeometryData]) -> E,
+        make: impl FnMut(
+            Hexagonish<(usize, u32, Hexagonish<u32>)>,
+            Coordinate,
+            &[GeometryData],
+            &mut E,
+        ) -> T,
+    ) -> (Self, Vec<GeometryData>, E) {
+        Self::try_chunked_dual(subdivisions, next_indices, ico_points, make_temporary, make)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`Hexasphere::chunked_dual`], but reports malformed geometry.
+    ///
+    /// # Errors
+    /// As in [`Hexasphere::try_make_and_dual`].
+    #[instrument(skip_all, fields(subdivisions = subdivisions, chunks = tracing::field::Empty))]
+    pub fn try_chunked_dual<E>(
         subdivisions: usize,
         mut next_indices: impl FnMut(&mut Vec<u32>),
         ico_points: &[Vec3A],
@@ -569,7 +1073,7 @@ impl<T> Hexasphere<T> {
             &[GeometryData],
             &mut E,
         ) -> T,
-    ) -> (Self, Vec<GeometryData>, E) {
+    ) -> Result<(Self, Vec<GeometryData>, E)> {
         let mut acc_coordinate_store = HashMap::new();
 
         let mut convert_to_dual_space =
@@ -585,13 +1089,13 @@ impl<T> Hexasphere<T> {
 
         while !indices.is_empty() {
             sets.push(indices.iter().copied().collect::<HashSet<_>>());
-            make_coordinate_store(&indices, &mut acc_coordinate_store);
+            try_make_coordinate_store(&indices, &mut acc_coordinate_store)?;
             indices.clear();
             next_indices(&mut indices);
         }
 
         for set in sets {
-            let dual_data = geometry_util::dual(
+            let dual_data = geometry_util::try_dual(
                 ico_points,
                 set.into_iter().map(|x| (x, None)),
                 &acc_coordinate_store,
@@ -602,37 +1106,119 @@ impl<T> Hexasphere<T> {
                         edges,
                     ));
                 },
-            );
+            )?;
 
             resulting_chunks.push(dual_data);
         }
 
+        tracing::Span::current().record("chunks", resulting_chunks.len());
+
         let mut temp = make_temporary(&resulting_chunks);
 
-        let surrounding =
-            Self::make_from_surrounding(subdivisions, &acc_coordinate_store, |old, coord| {
-                let results = convert_to_dual_space.remove(&old).unwrap();
+        let surrounding = Self::try_make_from_surrounding_with(
+            subdivisions,
+            &acc_coordinate_store,
+            |old, coord| {
+                let results = convert_to_dual_space
+                    .remove(&old)
+                    .ok_or(Error::MissingVertex { vertex: old })?;
 
-                make(results, coord, &resulting_chunks, &mut temp)
-            });
+                Ok(make(results, coord, &resulting_chunks, &mut temp))
+            },
+        )?;
 
-        (surrounding, resulting_chunks, temp)
+        Ok((surrounding, resulting_chunks, temp))
     }
 
     pub fn chunked(&self) -> Chunked {
         self.inner
     }
 
-    pub fn iter(&'_ self, coord: Coordinate) -> impl Iterator<Item = (&'_ T, Coordinate)> {
-        self.surrounding(coord).into_iter().map(|x| (&self[x], x))
+    /// Builds a [`NeighbourTable`] for this sphere, so that
+    /// [`Hexasphere::surrounding`] and everything using it
+    /// looks neighbours up rather than computing them.
+    ///
+    /// The table can be shared with other spheres of the same
+    /// size through [`Hexasphere::set_neighbour_table`].
+    pub fn cache_neighbours(&mut self) -> &Arc<NeighbourTable> {
+        self.neighbours
+            .get_or_insert_with(|| Arc::new(NeighbourTable::new(self.inner)))
     }
 
-    pub fn change_type<Q>(&self, mut to: impl FnMut(&T) -> Q) -> Hexasphere<Q> {
-        Hexasphere {
-            inner: self.inner,
+    pub fn neighbour_table(&self) -> Option<&Arc<NeighbourTable>> {
+        self.neighbours.as_ref()
+    }
+
+    /// Uses `table` for neighbour lookups, or goes back to
+    /// computing them if it's `None`.
+    ///
+    /// # Panics
+    /// If `table` is for a different number of subdivisions.
+    pub fn set_neighbour_table(&mut self, table: Option<Arc<NeighbourTable>>) {
+        if let Some(table) = &table {
+            assert_eq!(
+                table.chunked(),
+                self.inner,
+                "Neighbour table is for a different hexasphere!"
+            );
+        }
+        self.neighbours = table;
+    }
+
+    /// The same as [`Chunked::surrounding`], but
+    /// from the neighbour table if there is one.
+    ///
+    /// # Panics
+    /// If there is a neighbour table and `x` is not on this sphere.
+    pub fn surrounding(&self, x: Coordinate) -> Hexagonish<Coordinate> {
+        match &self.neighbours {
+            Some(table) => table.surrounding(x).try_into().unwrap(),
+            None => self.inner.surrounding(x),
+        }
+    }
+
+    /// The same as [`Chunked::find_blobs`], but
+    /// using the neighbour table if there is one.
+    pub fn find_blobs(&self, coords: impl Iterator<Item = Coordinate>) -> Option<Vec<Blob>> {
+        find_blobs(|x| self.surrounding(x), coords)
+    }
+
+    pub fn blob_borders(&self, blob: &Blob) -> Option<Vec<Vec<Coordinate>>> {
+        self.find_blobs(blob.borders.iter().copied())
+            .map(|x| x.into_iter().map(|blob| blob.borders).collect::<Vec<_>>())
+    }
+
+    /// # Panics
+    /// If `coordinates` isn't a ring; see [`Chunked::try_ring_order`].
+    pub fn ring_order(&self, coordinates: &mut [Coordinate], inside: &HashSet<Coordinate>) {
+        self.try_ring_order(coordinates, inside)
+            .unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    /// The same as [`Chunked::try_ring_order`], but
+    /// using the neighbour table if there is one.
+    ///
+    /// # Errors
+    /// As in [`Chunked::try_ring_order`].
+    pub fn try_ring_order(
+        &self,
+        coordinates: &mut [Coordinate],
+        inside: &HashSet<Coordinate>,
+    ) -> Result<()> {
+        ring_order(|x| self.surrounding(x), coordinates, inside)
+    }
+
+    pub fn iter(&'_ self, coord: Coordinate) -> impl Iterator<Item = (&'_ T, Coordinate)> {
+        self.surrounding(coord).into_iter().map(|x| (&self[x], x))
+    }
+
+    pub fn change_type<Q>(&self, mut to: impl FnMut(&T) -> Q) -> Hexasphere<Q> {
+        Hexasphere {
+            inner: self.inner,
             top: to(&self.top),
             bottom: to(&self.bottom),
             chunks: [0, 1, 2, 3, 4].map(|x| self.chunks[x].iter().map(&mut to).collect::<Vec<_>>()),
+            neighbours: self.neighbours.clone(),
         }
     }
 
@@ -652,16 +1238,34 @@ impl<T> Hexasphere<T> {
         coordinates.map(|x| &self[x])
     }
 
+    /// Mutably borrows several entries at once, or `None`
+    /// if any coordinate is asked for twice.
+    ///
+    /// # Panics
+    /// If any coordinate is invalid.
     pub fn get_many_mut<const N: usize>(
         &mut self,
         coordinates: [Coordinate; N],
     ) -> Option<[&mut T; N]> {
-        for val in coordinates {
-            assert!(self.inner.is_valid(val));
-            for test in coordinates {
-                if val == test {
-                    return None;
-                }
+        match self.try_get_many_mut(coordinates) {
+            Ok(vals) => Some(vals),
+            Err(Error::DuplicateCoordinate { .. }) => None,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// Mutably borrows several entries at once.
+    ///
+    /// # Errors
+    /// If any coordinate is invalid or asked for twice.
+    pub fn try_get_many_mut<const N: usize>(
+        &mut self,
+        coordinates: [Coordinate; N],
+    ) -> Result<[&mut T; N]> {
+        for (i, val) in coordinates.into_iter().enumerate() {
+            self.inner.check(val)?;
+            if coordinates[..i].contains(&val) {
+                return Err(Error::DuplicateCoordinate { coordinate: val });
             }
         }
 
@@ -681,9 +1285,7 @@ impl<T> Hexasphere<T> {
                     Coordinate::Bottom => &mut *bottom,
                     Coordinate::Inside { chunk, short, long } => {
                         let idx = short * 2 * (self.subdivisions + 1) + long;
-                        if idx > len {
-                            panic!("Coordinate is invalid!");
-                        }
+                        assert!(idx < len, "Coordinate is invalid!");
                         let ptr = coords[chunk as usize];
                         &mut *ptr.add(idx)
                     }
@@ -691,7 +1293,7 @@ impl<T> Hexasphere<T> {
             }
         });
 
-        Some(vals)
+        Ok(vals)
     }
 }
 
@@ -729,6 +1331,108 @@ where
     }
 }
 
+/// [`Chunked::find_blobs`], with neighbours from `surrounding`.
+fn find_blobs(
+    surrounding: impl Fn(Coordinate) -> Hexagonish<Coordinate>,
+    coords: impl Iterator<Item = Coordinate>,
+) -> Option<Vec<Blob>> {
+    let mut to_explore = vec![];
+    let mut all = coords.collect::<HashSet<_>>();
+
+    let mut yielded = Vec::new();
+
+    while !all.is_empty() {
+        let first = *all.iter().next().unwrap();
+
+        to_explore.push(first);
+
+        let mut current_contents = HashSet::new();
+        current_contents.insert(first);
+
+        let mut bordered = Vec::new();
+
+        while let Some(next) = to_explore.pop() {
+            let mut bordered_by_nothing = false;
+
+            for around in surrounding(next) {
+                let exists = all.contains(&around);
+
+                if exists && !current_contents.contains(&around) {
+                    to_explore.push(around);
+                    current_contents.insert(around);
+                }
+
+                bordered_by_nothing |= !exists;
+            }
+
+            if bordered_by_nothing {
+                bordered.push(next);
+            }
+        }
+
+        all.retain(|x| !current_contents.contains(x));
+
+        yielded.push(Blob {
+            contents: current_contents,
+            borders: bordered,
+        });
+    }
+
+    Some(yielded)
+}
+
+/// [`Chunked::try_ring_order`], with neighbours from `surrounding`.
+fn ring_order(
+    surrounding: impl Fn(Coordinate) -> Hexagonish<Coordinate>,
+    coordinates: &mut [Coordinate],
+    inside: &HashSet<Coordinate>,
+) -> Result<()> {
+    let mut visited = HashSet::new();
+    let all = coordinates.iter().copied().collect::<HashSet<_>>();
+    let mut ordered = Vec::new();
+
+    if all.len() < 2 || all.len() != coordinates.len() {
+        return Err(Error::NotARing);
+    }
+
+    let mut current = coordinates[0];
+
+    visited.insert(current);
+
+    ordered.push(current);
+
+    while visited.len() != all.len() {
+        let next = surrounding(current)
+            .into_iter()
+            .filter(|x| all.contains(x))
+            .find(|x| !visited.contains(x))
+            .ok_or(Error::NotARing)?;
+
+        visited.insert(next);
+        ordered.push(next);
+        current = next;
+    }
+
+    // Test for correct chirality.
+    let first = ordered[0];
+    let second = ordered[1];
+
+    let first_surrounding = surrounding(first);
+    let common_out = surrounding(second)
+        .into_iter()
+        .find(|x| first_surrounding.contains(x) && !inside.contains(x))
+        .ok_or(Error::NotARing)?;
+
+    coordinates.copy_from_slice(&ordered);
+
+    // Since it's outside, the first two should have reversed winding for it.
+    if first != rotate(second, 1, &surrounding(common_out)) {
+        coordinates.reverse();
+    }
+
+    Ok(())
+}
+
 fn rotate(previous: Coordinate, by: isize, surrounding: &[Coordinate]) -> Coordinate {
     let idx = surrounding.iter().position(|&x| x == previous).unwrap();
     surrounding[(idx as isize + by).rem_euclid(surrounding.len() as isize) as usize]
@@ -790,6 +1494,7 @@ impl<'a, T> IndexMut<&'a Coordinate> for Hexasphere<T> {
 
 /// Coordinate on a hexasphere.
 #[derive(Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Coordinate {
     #[default]
     Top,
@@ -821,6 +1526,7 @@ impl Ord for Coordinate {
         use Coordinate::*;
 
         match (self, other) {
+            (Top, Top) => Ordering::Equal,
             (Top, _) => Ordering::Greater,
             (_, Top) => Ordering::Less,
             (Bottom, Bottom) => Ordering::Equal,
@@ -859,53 +1565,241 @@ pub fn coord(chunk: u8, short: usize, long: usize) -> Coordinate {
     Coordinate::Inside { chunk, short, long }
 }
 
+/// Walks `coordinate_store` over one chunk, calling `make`
+/// in the order the chunk's entries are stored.
+/// Errors unless both poles, which are always vertices 0 and 11, are there.
+fn check_poles(coordinate_store: &impl Neighbours) -> Result<()> {
+    for vertex in [0, 11] {
+        if coordinate_store.neighbours(vertex).is_none() {
+            return Err(Error::MissingVertex { vertex });
+        }
+    }
+
+    Ok(())
+}
+
+fn walk_chunk<T>(
+    subdivisions: usize,
+    coordinate_store: &impl Neighbours,
+    chunk: u8,
+    mut make: impl FnMut(u32, Coordinate) -> Result<T>,
+) -> Result<Vec<T>> {
+    let neighbours = |vertex: u32| {
+        coordinate_store
+            .neighbours(vertex)
+            .ok_or(Error::MissingVertex { vertex })
+    };
+
+    let rotate_by = |previous: u32, this: u32, by: isize| {
+        let list = neighbours(this)?;
+        let idx = list
+            .iter()
+            .position(|&x| x == previous)
+            .ok_or(Error::InconsistentWinding { vertex: this })?;
+        Ok(list[(idx as isize + by).rem_euclid(list.len() as isize) as usize])
+    };
+
+    let mut data = Vec::with_capacity(2 * (subdivisions + 1) * (subdivisions + 1));
+    let mut short_prev = 0;
+    let mut short_root = *neighbours(0)?
+        .get(chunk as usize)
+        .ok_or(Error::InconsistentWinding { vertex: 0 })?;
+
+    for short in 0..subdivisions + 1 {
+        data.push(make(short_root, coord(chunk, short, 0))?);
+
+        let mut long_root = rotate_by(short_prev, short_root, -2)?;
+        let mut long_prev = short_root;
+
+        for long in 1..2 * (subdivisions + 1) {
+            data.push(make(long_root, coord(chunk, short, long))?);
+
+            let new_long_root = rotate_by(long_prev, long_root, -3)?;
+            long_prev = long_root;
+            long_root = new_long_root;
+        }
+
+        let new_short_root = rotate_by(short_prev, short_root, -3)?;
+        short_prev = short_root;
+        short_root = new_short_root;
+    }
+
+    Ok(data)
+}
+
 /// Creates an adjacency map in place from this set of coordinates.
 ///
 /// Preserves order and eliminates duplicates in each list.
+///
+/// # Panics
+/// If `indices` is malformed; see [`try_make_coordinate_store`].
 pub fn make_coordinate_store(
     indices: &[u32],
     coordinate_store: &mut HashMap<u32, Hexagonish<u32>>,
 ) {
-    assert_eq!(indices.len() % 3, 0);
-    indices.chunks(3).for_each(|x| {
-        if let &[a, b, c] = x {
-            let mut store_entry = |i, j, k| match coordinate_store.entry(i) {
-                Entry::Vacant(x) => {
-                    x.insert(ArrayVec::from_iter([j, k]));
-                }
-                Entry::Occupied(x) => {
-                    let list = x.into_mut();
-                    if let Some(idx_j) = list.iter().position(|&z| z == j) {
-                        if list[(idx_j + 1) % list.len()] != k {
-                            list.insert(idx_j + 1, k);
-                        }
-                    } else if let Some(idx_k) = list.iter().position(|&z| z == k) {
-                        list.insert(idx_k, j);
-                    } else {
-                        list.push(j);
-                        list.push(k);
-                    }
-                }
-            };
+    try_make_coordinate_store(indices, coordinate_store).unwrap_or_else(|e| panic!("{e}"));
+}
 
+/// Like [`make_coordinate_store`], but reports malformed indices.
+///
+/// # Errors
+/// If `indices` isn't a list of triangles, or any vertex ends up with
+/// more than 6 neighbours. `coordinate_store` may be partially filled.
+#[instrument(skip_all, fields(triangles = indices.len() / 3))]
+pub fn try_make_coordinate_store(
+    indices: &[u32],
+    coordinate_store: &mut HashMap<u32, Hexagonish<u32>>,
+) -> Result<()> {
+    if indices.len() % 3 != 0 {
+        return Err(Error::MalformedIndices { len: indices.len() });
+    }
+
+    for x in indices.chunks(3) {
+        if let &[a, b, c] = x {
             // Order of the entries in the arrays matters!
-            store_entry(a, b, c);
-            store_entry(b, c, a);
-            store_entry(c, a, b);
+            for (i, j, k) in [(a, b, c), (b, c, a), (c, a, b)] {
+                adjacency::link(coordinate_store.entry(i).or_default(), i, [j, k])?;
+            }
         }
-    });
+    }
+
+    Ok(())
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Blob {
     pub contents: HashSet<Coordinate>,
     pub borders: Vec<Coordinate>,
 }
 
+impl Blob {
+    /// Walks around the edge of the blob, for drawing its outline.
+    ///
+    /// Each loop is a list of tile edges, as `(inside, outside)` pairs
+    /// of a tile in the blob and its neighbour outside. Going along a
+    /// loop, the blob is always on the same side: the outer loop goes
+    /// around it in the winding of [`Chunked::surrounding`], and the
+    /// holes go the other way. On the dual geometry, the edge towards
+    /// neighbour `i` runs from corner `i - 1` to corner `i` of
+    /// [`Chunked::corners_of`], so each edge ends where the next begins.
+    ///
+    /// Since every loop encloses something on a sphere, the outer loop
+    /// is taken to be the one around the largest connected part of
+    /// everything outside the blob. There are no loops at all if the blob
+    /// covers the whole sphere.
+    ///
+    /// The edges are found from `contents` alone, so the loops
+    /// are still closed if `borders` has been left out of date.
+    ///
+    /// # Panics
+    /// If the blob isn't on `sphere`.
+    pub fn boundary_loops(&self, sphere: Chunked) -> BoundaryLoops {
+        // Where to go from each edge, keeping the blob on the same side.
+        let mut next = HashMap::new();
+        for &inside in &self.contents {
+            let around = sphere.surrounding(inside);
+            for &outside in around.iter().filter(|x| !self.contents.contains(x)) {
+                // The edge ends at the corner shared with the tile after
+                // `outside`. If that's in the blob, the outline turns
+                // around the corner onto its edge with `outside`.
+                let turn = rotate(outside, 1, &around);
+                let following = if self.contents.contains(&turn) {
+                    (turn, outside)
+                } else {
+                    (inside, turn)
+                };
+                next.insert((inside, outside), following);
+            }
+        }
+
+        let mut edges = next.keys().copied().collect::<Vec<_>>();
+        edges.sort_unstable();
+
+        let mut walked = HashSet::new();
+        let mut loops = Vec::new();
+        for start in edges {
+            if walked.contains(&start) {
+                continue;
+            }
+
+            let mut edge_loop = vec![start];
+            walked.insert(start);
+            let mut current = next[&start];
+            while current != start {
+                edge_loop.push(current);
+                walked.insert(current);
+                current = next[&current];
+            }
+            loops.push(edge_loop);
+        }
+
+        // How much is on the other side of each loop.
+        let mut outside = HashSet::new();
+        let sizes = loops
+            .iter()
+            .map(|edge_loop| {
+                let start = edge_loop[0].1;
+                if !outside.insert(start) {
+                    return 0;
+                }
+
+                let mut to_explore = vec![start];
+                let mut size = 0;
+                while let Some(x) = to_explore.pop() {
+                    size += 1;
+                    for around in sphere.surrounding(x) {
+                        if !self.contents.contains(&around) && outside.insert(around) {
+                            to_explore.push(around);
+                        }
+                    }
+                }
+                size
+            })
+            .collect::<Vec<_>>();
+
+        let Some(largest) = (0..loops.len()).max_by_key(|&i| (sizes[i], Reverse(i))) else {
+            return BoundaryLoops::default();
+        };
+
+        let outer = loops.remove(largest);
+        BoundaryLoops {
+            outer,
+            holes: loops,
+        }
+    }
+}
+
+/// The outline of a [`Blob`] from [`Blob::boundary_loops`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BoundaryLoops {
+    pub outer: Vec<(Coordinate, Coordinate)>,
+    pub holes: Vec<Vec<(Coordinate, Coordinate)>>,
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{Chunked, Coordinate};
-    use std::collections::{HashMap, HashSet};
+    use crate::adjacency::{Adjacency, NeighbourTable, Neighbours};
+    use crate::{Chunked, Coordinate, Error, Hexasphere};
+    use glam::Vec3A;
+    use std::collections::{HashMap, HashSet, VecDeque};
+
+    fn bfs(hsphere: Chunked, from: Coordinate) -> HashMap<Coordinate, usize> {
+        let mut distances = HashMap::from([(from, 0)]);
+        let mut queue = VecDeque::from([from]);
+
+        while let Some(x) = queue.pop_front() {
+            let next = distances[&x] + 1;
+            for z in hsphere.surrounding(x) {
+                distances.entry(z).or_insert_with(|| {
+                    queue.push_back(z);
+                    next
+                });
+            }
+        }
+
+        distances
+    }
 
     #[test]
     fn coord_surrounding() {
@@ -941,4 +1835,1255 @@ mod tests {
             });
         }
     }
+
+    #[test]
+    fn distance_matches_bfs() {
+        for subdivisions in 0..5 {
+            let hsphere = Chunked { subdivisions };
+
+            for a in hsphere.iter_all() {
+                for (b, expected) in bfs(hsphere, a) {
+                    assert_eq!(
+                        hsphere.distance(a, b),
+                        expected,
+                        "{a:?} -> {b:?} at {subdivisions} subdivisions"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn coordinate_order_is_total() {
+        use std::cmp::Ordering;
+
+        assert_eq!(Coordinate::Top.cmp(&Coordinate::Top), Ordering::Equal);
+
+        let hsphere = Chunked { subdivisions: 2 };
+        let all = hsphere.iter_all().collect::<Vec<_>>();
+        for a in &all {
+            for b in &all {
+                assert_eq!(a.cmp(b), b.cmp(a).reverse(), "{a:?} vs {b:?}");
+                assert_eq!(a.cmp(b) == Ordering::Equal, a == b, "{a:?} vs {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn ring_one_is_surrounding() {
+        let hsphere = Chunked { subdivisions: 3 };
+
+        for x in hsphere.iter_all() {
+            assert_eq!(
+                hsphere.ring(x, 1).collect::<Vec<_>>(),
+                hsphere.surrounding(x).to_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn rings_and_spirals() {
+        let hsphere = Chunked { subdivisions: 4 };
+
+        for center in hsphere.iter_all() {
+            let distances = bfs(hsphere, center);
+            let furthest = *distances.values().max().unwrap();
+            let mut spiral = Vec::new();
+
+            for radius in 0..=furthest {
+                let ring = hsphere.ring(center, radius).collect::<Vec<_>>();
+                let expected = distances
+                    .iter()
+                    .filter(|&(_, &d)| d == radius)
+                    .map(|(&x, _)| x)
+                    .collect::<HashSet<_>>();
+
+                assert_eq!(ring.len(), expected.len());
+                assert_eq!(ring.iter().copied().collect::<HashSet<_>>(), expected);
+
+                if (1..=hsphere.subdivisions).contains(&radius) {
+                    for (i, x) in ring.iter().enumerate() {
+                        let next = ring[(i + 1) % ring.len()];
+                        assert!(
+                            hsphere.surrounding(*x).contains(&next),
+                            "{x:?} -> {next:?} around {center:?} at {radius}"
+                        );
+                    }
+                }
+
+                spiral.extend(ring);
+            }
+
+            assert_eq!(hsphere.spiral(center, furthest).collect::<Vec<_>>(), spiral);
+        }
+    }
+
+    #[test]
+    fn lines() {
+        for subdivisions in [0, 3, 8] {
+            let hsphere = Chunked::new(subdivisions);
+            let count = hsphere.tile_count() as u32;
+
+            for i in (0..count).step_by(13) {
+                for j in (0..count).step_by(11) {
+                    let (a, b) = (hsphere.from_index(i), hsphere.from_index(j));
+                    let line = hsphere.line(a, b).collect::<Vec<_>>();
+
+                    assert_eq!(line.first(), Some(&a));
+                    assert_eq!(line.last(), Some(&b));
+                    assert!(line
+                        .windows(2)
+                        .all(|x| hsphere.surrounding(x[0]).contains(&x[1])));
+                    assert_eq!(line.len(), hsphere.distance(a, b) + 1, "{a:?} -> {b:?}");
+                    assert_eq!(line.iter().collect::<HashSet<_>>().len(), line.len());
+
+                    let mut back = hsphere.line(b, a).collect::<Vec<_>>();
+                    back.reverse();
+                    assert_eq!(back, line);
+                }
+            }
+        }
+
+        let hsphere = Chunked::new(6);
+        let x = hsphere.from_index(50);
+        assert_eq!(hsphere.line(x, x).collect::<Vec<_>>(), [x]);
+        for around in hsphere.surrounding(x) {
+            assert_eq!(hsphere.line(x, around).collect::<Vec<_>>(), [x, around]);
+        }
+    }
+
+    #[test]
+    fn tile_index_round_trip() {
+        for subdivisions in 0..6 {
+            let hsphere = Chunked { subdivisions };
+            assert_eq!(hsphere.iter_all().count(), hsphere.tile_count());
+
+            for (i, x) in hsphere.iter_all().enumerate() {
+                assert_eq!(hsphere.to_index(x), i as u32);
+                assert_eq!(hsphere.from_index(i as u32), x);
+            }
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "isn't on a sphere")]
+    fn tile_index_of_invalid_coordinate() {
+        // Would alias `Inside { chunk: 0, short: 1, long: 0 }` otherwise.
+        Chunked::new(2).to_index(Coordinate::Inside {
+            chunk: 0,
+            short: 0,
+            long: 6,
+        });
+    }
+
+    #[test]
+    fn native_geometry() {
+        let hsphere = Chunked::new(4);
+        let edge = hsphere
+            .center_of(Coordinate::Top)
+            .angle_between(hsphere.center_of(hsphere.surrounding(Coordinate::Top)[0]));
+
+        for x in hsphere.iter_all() {
+            let center = hsphere.center_of(x);
+            assert!((center.length() - 1.0).abs() < 1e-5);
+
+            let around = hsphere.surrounding(x);
+            for (i, y) in around.iter().enumerate() {
+                let next = hsphere.center_of(around[(i + 1) % around.len()]);
+                let y = hsphere.center_of(*y);
+
+                // Neighbours are evenly spaced and wound counter-clockwise.
+                let angle = center.angle_between(y);
+                assert!(angle > 0.8 * edge && angle < 1.3 * edge, "{x:?}");
+                assert!((y - center).cross(next - center).dot(center) > 0.0, "{x:?}");
+            }
+        }
+
+        let (sphere, geometry, ()) = Hexasphere::make_dual(4, |_| (), |index, _, _, _, ()| index);
+        for x in hsphere.iter_all() {
+            let center = geometry.points[sphere[x] as usize];
+            assert!(center.distance(hsphere.center_of(x)) < 0.05);
+        }
+
+        for triangle in geometry.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| geometry.points[triangle[i] as usize]);
+            assert!((b - a).cross(c - a).dot(a) > 0.0);
+        }
+    }
+
+    #[test]
+    fn coordinate_at_nearest_center() {
+        let hsphere = Chunked::new(5);
+        let centers = hsphere
+            .iter_all()
+            .map(|x| (x, hsphere.center_of(x)))
+            .collect::<Vec<_>>();
+
+        for &(x, center) in &centers {
+            assert_eq!(hsphere.coordinate_at(center), x);
+        }
+
+        assert!(hsphere
+            .center_of(Coordinate::Top)
+            .abs_diff_eq(Vec3A::Y, 1e-6));
+        assert_eq!(
+            hsphere.coordinate_at_latlon(std::f32::consts::FRAC_PI_2, 0.0),
+            Coordinate::Top
+        );
+
+        for lat in -20..=20 {
+            for lon in 0..40 {
+                let (lat, lon) = (lat as f32 * 0.078, lon as f32 * 0.157);
+                // North is `+y` and longitude 0 is `+x`, with east towards `-z`.
+                let direction =
+                    Vec3A::new(lat.cos() * lon.cos(), lat.sin(), -lat.cos() * lon.sin());
+                let found = hsphere.coordinate_at_latlon(lat, lon);
+                let nearest = centers
+                    .iter()
+                    .map(|&(_, center)| center.dot(direction))
+                    .fold(f32::MIN, f32::max);
+
+                assert!(
+                    hsphere.center_of(found).dot(direction) >= nearest - 1e-6,
+                    "{lat} {lon} gave {found:?}"
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let hsphere = Hexasphere::from_subdivisions(3, |x| x);
+        let json = serde_json::to_string(&hsphere).unwrap();
+        let loaded: Hexasphere<Coordinate> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, hsphere);
+
+        let mut short = hsphere.clone();
+        short.chunks[2].pop();
+        let json = serde_json::to_string(&short).unwrap();
+        assert!(serde_json::from_str::<Hexasphere<Coordinate>>(&json).is_err());
+    }
+
+    #[test]
+    fn file_round_trip() {
+        use crate::file::{self, Compression};
+        use std::io::Cursor;
+
+        let hsphere = Hexasphere::from_subdivisions(6, |x| match x {
+            Coordinate::Inside { chunk, long, .. } => u16::from(chunk) * 100 + long as u16 / 4,
+            _ => 7,
+        });
+
+        for compression in [Compression::None, Compression::RunLength] {
+            let mut bytes = Vec::new();
+            file::write(&hsphere, &mut bytes, compression).unwrap();
+            assert_eq!(file::read::<u16>(&bytes[..]).unwrap(), hsphere);
+
+            let (header, chunk) = file::read_chunk::<u16>(Cursor::new(&bytes), 3).unwrap();
+            assert_eq!(header.subdivisions, 6);
+            assert_eq!(chunk, hsphere.chunks[3]);
+
+            // Claim a different subdivision than the chunks were written with.
+            bytes[7] = 5;
+            assert!(file::read::<u16>(&bytes[..]).is_err());
+            assert!(file::read_chunk::<u16>(Cursor::new(&bytes), 0).is_err());
+        }
+
+        // A forged header, where the first chunk is one run claiming all of a huge sphere.
+        let subdivisions = 60_000u32;
+        let entries = 2 * (u64::from(subdivisions) + 1).pow(2);
+        let mut forged = Vec::new();
+        forged.extend(file::MAGIC);
+        forged.extend(file::VERSION.to_le_bytes());
+        forged.push(1);
+        forged.extend(subdivisions.to_le_bytes());
+        for _ in 0..5 {
+            forged.extend((4u64 + 2 + 1 + 4 + 5 * 16 + 2).to_le_bytes());
+            forged.extend(18u64.to_le_bytes());
+        }
+        forged.extend([0; 2]);
+        forged.extend(entries.to_le_bytes());
+        forged.extend(entries.to_le_bytes());
+        forged.extend([0; 2]);
+        assert!(file::read::<u8>(&forged[..]).is_err());
+        assert!(file::read_chunk::<u8>(Cursor::new(&forged), 0).is_err());
+    }
+
+    #[test]
+    fn errors_instead_of_panics() {
+        let mut hsphere = Hexasphere::from_subdivisions(2, |_| 0);
+        let a = Coordinate::Inside {
+            chunk: 1,
+            short: 1,
+            long: 3,
+        };
+        let far = Coordinate::Inside {
+            chunk: 3,
+            short: 0,
+            long: 5,
+        };
+        let invalid = Coordinate::Inside {
+            chunk: 5,
+            short: 0,
+            long: 0,
+        };
+
+        let [x, y] = hsphere.try_get_many_mut([a, far]).unwrap();
+        *x = 1;
+        *y = 2;
+        assert_eq!((hsphere[a], hsphere[far]), (1, 2));
+        assert_eq!(
+            hsphere.try_get_many_mut([a, far, a]).err(),
+            Some(Error::DuplicateCoordinate { coordinate: a })
+        );
+        assert!(hsphere.get_many_mut([a, a]).is_none());
+        assert!(matches!(
+            hsphere.try_get_many_mut([invalid]),
+            Err(Error::InvalidCoordinate { .. })
+        ));
+
+        let chunked = hsphere.chunked();
+        assert!(matches!(
+            chunked.try_continue_line(a, far, |_, _, (x, _)| x),
+            Err(Error::NotAdjacent { .. })
+        ));
+        assert!(chunked
+            .try_continue_line(a, chunked.surrounding(a)[0], |_, _, (x, _)| x)
+            .is_ok());
+
+        let mut ring = [a, far];
+        assert_eq!(
+            chunked.try_ring_order(&mut ring, &HashSet::new()),
+            Err(Error::NotARing)
+        );
+        assert_eq!(ring, [a, far]);
+
+        let mut store = HashMap::new();
+        assert_eq!(
+            crate::try_make_coordinate_store(&[0, 1, 2, 3], &mut store),
+            Err(Error::MalformedIndices { len: 4 })
+        );
+        assert!(Hexasphere::try_from_hexasphere_geometry(2, &[0, 1, 2], |_, _| ()).is_err());
+        // A stray id mustn't size the map by itself.
+        let stray = [0, 1, u32::MAX];
+        assert_eq!(
+            Adjacency::from_indices(&stray),
+            Err(Error::MalformedIndices { len: 3 })
+        );
+        #[cfg(feature = "rayon")]
+        assert_eq!(
+            Adjacency::par_from_indices(&stray),
+            Err(Error::MalformedIndices { len: 3 })
+        );
+        assert!(
+            Hexasphere::try_make_and_dual(0, &[0, 1, 2], &[], |_| (), |_, _, _, _, ()| ()).is_err()
+        );
+
+        // Well formed index buffers, but missing a pole.
+        let octahedron = [
+            0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 1, 5, 2, 1, 5, 3, 2, 5, 4, 3, 5, 1, 4,
+        ];
+        let points = [Vec3A::Y; 6];
+        for (indices, vertex) in [(&octahedron[..], 11), (&[1, 2, 3, 3, 2, 1][..], 0)] {
+            let missing = Err(Error::MissingVertex { vertex });
+            let made = Hexasphere::<()>::try_make_and_dual(
+                0,
+                indices,
+                &points,
+                |_| (),
+                |_, _, _, _, ()| panic!("Made a tile from bad geometry!"),
+            );
+            assert_eq!(made.map(|_| ()), missing);
+            let made = Hexasphere::<()>::try_from_hexasphere_geometry(0, indices, |_, _| {
+                panic!("Made a tile from bad geometry!")
+            });
+            assert_eq!(made.map(|_| ()), missing);
+
+            #[cfg(feature = "rayon")]
+            {
+                let store = Adjacency::from_indices(indices).unwrap();
+                let made = Hexasphere::<()>::try_par_make_from_surrounding(0, &store, |_, _| {
+                    panic!("Made a tile from bad geometry!")
+                });
+                assert_eq!(made.map(|_| ()), missing);
+            }
+        }
+    }
+
+    /// Triangles for the sphere's own topology, indexed the
+    /// way `make_from_surrounding` expects, with the poles at 0 and 11.
+    fn native_indices(hsphere: Chunked) -> (Vec<u32>, impl Fn(Coordinate) -> u32) {
+        let old = move |x: Coordinate| match hsphere.to_index(x) {
+            1 => 11,
+            11 => 1,
+            i => i,
+        };
+
+        let mut indices = Vec::new();
+        for x in hsphere.iter_all() {
+            let around = hsphere.surrounding(x);
+            for (i, &a) in around.iter().enumerate() {
+                indices.extend([old(x), old(a), old(around[(i + 1) % around.len()])]);
+            }
+        }
+
+        (indices, old)
+    }
+
+    #[test]
+    fn native_topology_round_trips() {
+        for subdivisions in 0..4 {
+            let hsphere = Chunked::new(subdivisions);
+            let (indices, old) = native_indices(hsphere);
+
+            let (made, store) =
+                Hexasphere::try_from_hexasphere_geometry(subdivisions, &indices, |i, _| i).unwrap();
+            assert!(hsphere.iter_all().all(|x| made[x] == old(x)));
+
+            let flat = Adjacency::from_indices(&indices).unwrap();
+            assert_eq!(flat, Adjacency::from(&store));
+
+            let mut points = vec![Vec3A::ZERO; hsphere.tile_count()];
+            for x in hsphere.iter_all() {
+                points[old(x) as usize] = hsphere.center_of(x);
+            }
+            let mut translations = vec![None; hsphere.tile_count()];
+            let serial = crate::geometry_util::dual(
+                &points,
+                flat.vertices().map(|x| (x, None)),
+                &flat,
+                |old, new, edges| translations[old as usize] = Some((new, edges)),
+            );
+            let (dense, dense_translations) =
+                crate::geometry_util::dense_dual(&points, &flat, false).unwrap();
+            assert_eq!(dense.points, serial.points);
+            assert_eq!(dense.indices, serial.indices);
+            assert_eq!(dense_translations, translations);
+            assert_eq!(
+                Hexasphere::make_from_surrounding(subdivisions, &flat, |i, _| i),
+                made
+            );
+        }
+    }
+
+    #[test]
+    fn neighbour_table_matches_computed() {
+        for subdivisions in [0, 1, 4] {
+            let hsphere = Chunked::new(subdivisions);
+            let table = NeighbourTable::new(hsphere);
+
+            for x in hsphere.iter_all() {
+                let around = hsphere.surrounding(x);
+                assert_eq!(table.surrounding(x), &around[..]);
+
+                let ids = around
+                    .iter()
+                    .map(|&y| hsphere.to_index(y))
+                    .collect::<Vec<_>>();
+                assert_eq!(table.surrounding_ids(hsphere.to_index(x)), ids);
+                assert_eq!(table.ids().neighbours(hsphere.to_index(x)), Some(&ids[..]));
+            }
+
+            let plain = Hexasphere::from_subdivisions(subdivisions, |x| x);
+            let mut cached = plain.clone();
+            cached.cache_neighbours();
+            assert_eq!(cached, plain);
+
+            let some = hsphere
+                .iter_all()
+                .filter(|&x| hsphere.to_index(x) % 3 != 0)
+                .collect::<Vec<_>>();
+            let sizes = |blobs: Vec<crate::Blob>| {
+                let mut sizes = blobs.iter().map(|x| x.contents.len()).collect::<Vec<_>>();
+                sizes.sort_unstable();
+                sizes
+            };
+            assert_eq!(
+                sizes(cached.find_blobs(some.iter().copied()).unwrap()),
+                sizes(hsphere.find_blobs(some.iter().copied()).unwrap())
+            );
+        }
+    }
+
+    #[cfg(feature = "algorithms")]
+    #[test]
+    fn distance_fields() {
+        use crate::algorithms::distance_field;
+
+        let hsphere = Hexasphere::from_subdivisions(4, |_| ());
+        let step = |_: &Hexasphere<()>, _, _| Some(1);
+
+        let a = Coordinate::Top;
+        let b = hsphere.from_index(40);
+        let field = distance_field([a], &hsphere, step, None);
+        let both = distance_field([a, b], &hsphere, step, None);
+        let near = distance_field([a, b], &hsphere, step, Some(2));
+
+        for x in hsphere.iter_all() {
+            let (to_a, to_b) = (hsphere.distance(a, x) as u32, hsphere.distance(b, x) as u32);
+            assert_eq!(field[x], Some(to_a));
+            assert_eq!(both[x], Some(to_a.min(to_b)));
+            assert_eq!(near[x], Some(to_a.min(to_b)).filter(|&d| d <= 2));
+        }
+
+        // Walls around the top pentagon cut it off from everything else.
+        let walled = distance_field(
+            [b],
+            &hsphere,
+            |sphere, _, to| (!sphere.surrounding(Coordinate::Top).contains(&to)).then_some(1),
+            None,
+        );
+        assert_eq!(walled[Coordinate::Top], None);
+        assert!(hsphere
+            .surrounding(Coordinate::Top)
+            .iter()
+            .all(|&x| walled[x].is_none()));
+        assert_eq!(walled[b], Some(0));
+    }
+
+    #[cfg(feature = "algorithms")]
+    #[test]
+    fn flow_field_updates() {
+        use crate::algorithms::FlowField;
+
+        let chunked = Chunked::new(5);
+        let mut hsphere =
+            Hexasphere::from_subdivisions(5, |x| 1 + chunked.to_index(x) as usize % 3);
+        hsphere.cache_neighbours();
+        let weight = |sphere: &Hexasphere<usize>, _, to| (sphere[to] != 0).then_some(sphere[to]);
+
+        let goals = [Coordinate::Bottom, hsphere.from_index(100)];
+        let mut field = FlowField::new(goals, &hsphere, weight);
+
+        let check = |field: &FlowField, sphere: &Hexasphere<usize>| {
+            assert_eq!(field.costs(), FlowField::new(goals, sphere, weight).costs());
+            for x in sphere.iter_all() {
+                match field.directions()[x] {
+                    Some(to) => assert_eq!(
+                        field.costs()[x],
+                        field.costs()[to].map(|c| c + weight(sphere, x, to).unwrap() as u32)
+                    ),
+                    None => assert!(goals.contains(&x) || field.costs()[x].is_none()),
+                }
+            }
+        };
+        check(&field, &hsphere);
+
+        // Wall off a ring, then open it up again and make it cheap.
+        let ring = hsphere.ring(Coordinate::Top, 3).collect::<Vec<_>>();
+        for &x in &ring {
+            hsphere[x] = 0;
+        }
+        field.update(&hsphere, ring.iter().copied(), weight);
+        check(&field, &hsphere);
+        assert_eq!(field.costs()[Coordinate::Top], None);
+
+        for &x in &ring {
+            hsphere[x] = 1;
+        }
+        field.update(&hsphere, ring.iter().copied(), weight);
+        check(&field, &hsphere);
+        assert!(field.costs()[Coordinate::Top].is_some());
+    }
+
+    #[cfg(feature = "algorithms")]
+    #[test]
+    fn fields_of_view() {
+        use crate::algorithms::{field_of_view, line_of_sight};
+
+        let hsphere = Hexasphere::from_subdivisions(8, |_| ());
+        for origin in [Coordinate::Top, hsphere.from_index(300)] {
+            let spiral = hsphere.spiral(origin, 4).collect::<HashSet<_>>();
+            assert_eq!(field_of_view(&hsphere, origin, 4, |_| false), spiral);
+
+            // A closed wall hides everything behind it, but not itself.
+            let wall = hsphere.ring(origin, 2).collect::<HashSet<_>>();
+            let inside = hsphere.spiral(origin, 2).collect::<HashSet<_>>();
+            assert_eq!(
+                field_of_view(&hsphere, origin, 4, |x| wall.contains(&x)),
+                inside
+            );
+
+            // A single pillar hides what's straight behind it, and only that.
+            let pillar = hsphere.surrounding(origin)[0];
+            let behind = hsphere
+                .ring(origin, 4)
+                .find(|&x| {
+                    hsphere.line(origin, x).nth(1) == Some(pillar)
+                        && hsphere.distance(pillar, x) == 3
+                })
+                .unwrap();
+            let seen = field_of_view(&hsphere, origin, 4, |x| x == pillar);
+            assert!(seen.contains(&pillar));
+            assert!(!seen.contains(&behind));
+            assert!(hsphere.surrounding(origin).iter().all(|x| seen.contains(x)));
+            assert!(seen.len() < spiral.len());
+
+            assert!(line_of_sight(&hsphere, origin, pillar, |x| x == pillar));
+            assert!(!line_of_sight(&hsphere, origin, behind, |x| x == pillar));
+        }
+    }
+
+    #[test]
+    fn region_graph() {
+        use crate::regions::Regions;
+
+        let chunked = Chunked::new(6);
+        let labels = Hexasphere::from_subdivisions(6, |x| chunked.to_index(x) * 7 / 5 % 3);
+        let regions = Regions::new(&labels);
+
+        let mut seen = HashSet::new();
+        for (id, region) in regions.regions().iter().enumerate() {
+            let blobs = chunked.find_blobs(region.blob.contents.iter().copied());
+            assert_eq!(blobs.map(|x| x.len()), Some(1));
+            for &x in &region.blob.contents {
+                assert_eq!(labels[x], region.label);
+                assert_eq!(regions.region_of(x), id as u32);
+                assert!(seen.insert(x));
+            }
+        }
+        assert_eq!(seen.len(), chunked.tile_count());
+
+        let mut edges = HashMap::new();
+        for x in chunked.iter_all() {
+            for around in chunked.surrounding(x) {
+                let (a, b) = (regions.region_of(x), regions.region_of(around));
+                if labels[x] == labels[around] {
+                    assert_eq!(a, b);
+                } else if a < b {
+                    *edges.entry((a, b)).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let borders = regions.borders().collect::<Vec<_>>();
+        assert_eq!(borders.len(), edges.len());
+        for (a, b, len) in borders {
+            assert_eq!(edges[&(a, b)], len);
+            assert_eq!(regions.border_length(b, a), len);
+            assert!(regions.neighbours(b).contains(&(a, len)));
+        }
+    }
+
+    #[test]
+    fn blob_boundary_loops() {
+        let hsphere = Chunked::new(8);
+        let center = hsphere.from_index(400);
+
+        // A thick ring around `center`, with a hole in it, and a single tile.
+        let ring = (1..=4)
+            .flat_map(|r| hsphere.ring(center, r))
+            .collect::<HashSet<_>>();
+        let blob = |contents: &HashSet<Coordinate>| {
+            let mut blobs = hsphere.find_blobs(contents.iter().copied()).unwrap();
+            assert_eq!(blobs.len(), 1);
+            blobs.pop().unwrap().boundary_loops(hsphere)
+        };
+
+        let connected = |edges: &[(Coordinate, Coordinate)]| {
+            let corners = |(inside, outside): (Coordinate, Coordinate)| {
+                let around = hsphere.surrounding(inside);
+                let i = around.iter().position(|&x| x == outside).unwrap();
+                let n = around.len();
+                (around[(i + n - 1) % n], around[i], around[(i + 1) % n])
+            };
+            (0..edges.len()).all(|i| {
+                let (_, _, end) = corners(edges[i]);
+                let next = edges[(i + 1) % edges.len()];
+                // The next edge begins at the corner of `inside`, `outside` and `end`.
+                [next.0, next.1] == [edges[i].0, end] || [next.0, next.1] == [end, edges[i].1]
+            })
+        };
+
+        let single = blob(&HashSet::from([center]));
+        assert!(single.holes.is_empty());
+        assert!(connected(&single.outer));
+        assert_eq!(
+            single.outer.iter().copied().collect::<HashSet<_>>(),
+            hsphere
+                .surrounding(center)
+                .into_iter()
+                .map(|x| (center, x))
+                .collect()
+        );
+
+        // Same as `surrounding`, apart from where it starts.
+        let around = hsphere.surrounding(center);
+        let start = around.iter().position(|&x| x == single.outer[0].1).unwrap();
+        let winding = (0..around.len())
+            .map(|i| around[(start + i) % around.len()])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            single.outer.iter().map(|x| x.1).collect::<Vec<_>>(),
+            winding
+        );
+
+        let mut holed = ring.clone();
+        holed.remove(&hsphere.ring(center, 3).next().unwrap());
+        let loops = blob(&holed);
+        assert_eq!(loops.holes.len(), 2);
+        assert!(loops.holes.iter().all(|x| x.len() == 6));
+
+        // The hole where `center` was goes around it backwards.
+        let middle = loops.holes.iter().find(|x| x[0].1 == center).unwrap();
+        let mut backwards = middle.iter().map(|x| x.0).collect::<Vec<_>>();
+        backwards.reverse();
+        let start = around.iter().position(|&x| x == backwards[0]).unwrap();
+        assert!((0..around.len()).all(|i| around[(start + i) % around.len()] == backwards[i]));
+
+        let mut edges = HashSet::new();
+        for edge_loop in std::iter::once(&loops.outer).chain(&loops.holes) {
+            assert!(connected(edge_loop));
+            edges.extend(edge_loop.iter().copied());
+        }
+        let expected = holed
+            .iter()
+            .flat_map(|&x| hsphere.surrounding(x).into_iter().map(move |y| (x, y)))
+            .filter(|(_, y)| !holed.contains(y))
+            .collect::<HashSet<_>>();
+        assert_eq!(edges, expected);
+        assert!(loops
+            .outer
+            .iter()
+            .all(|&(_, outside)| hsphere.distance(center, outside) == 5));
+
+        let all = hsphere.iter_all().collect::<HashSet<_>>();
+        assert_eq!(blob(&all), crate::BoundaryLoops::default());
+
+        // Touching pentagons, from inside and out.
+        for pentagon in hsphere
+            .iter_all()
+            .filter(|&x| hsphere.surrounding(x).len() == 5)
+        {
+            let single = blob(&HashSet::from([pentagon]));
+            assert_eq!(single.outer.len(), 5);
+            assert!(connected(&single.outer));
+
+            let around = hsphere.ring(pentagon, 1).collect::<HashSet<_>>();
+            let loops = blob(&around);
+            assert_eq!(loops.holes.len(), 1);
+            assert_eq!(loops.holes[0].len(), 5);
+            assert!(loops.holes[0]
+                .iter()
+                .all(|&(_, outside)| outside == pentagon));
+            assert!(connected(&loops.outer) && connected(&loops.holes[0]));
+        }
+
+        // Out of date borders don't leave the loops open.
+        let mut stale = hsphere.find_blobs(holed.iter().copied()).unwrap().remove(0);
+        stale.borders.truncate(1);
+        assert_eq!(stale.boundary_loops(hsphere), loops);
+    }
+
+    #[test]
+    fn incremental_components() {
+        use crate::regions::{ComponentEvent, Components};
+
+        let chunked = Chunked::new(5);
+        let mut included = Hexasphere::from_subdivisions(5, |x| chunked.to_index(x) % 3 != 0);
+        let mut components = Components::new(&included, |&x| x);
+
+        // Compare against flooding everything from scratch.
+        let check = |components: &Components, included: &Hexasphere<bool>| {
+            let set = chunked.iter_all().filter(|&x| included[x]);
+            let blobs = chunked.find_blobs(set).unwrap_or_default();
+            assert_eq!(components.components().count(), blobs.len());
+            for blob in blobs {
+                let id = components.component_of(*blob.contents.iter().next().unwrap());
+                assert_eq!(components.len(id.unwrap()), blob.contents.len());
+                assert!(blob
+                    .contents
+                    .iter()
+                    .all(|&x| components.component_of(x) == id));
+            }
+        };
+        check(&components, &included);
+
+        let mut seed = 1u64;
+        let (mut merges, mut splits) = (0, 0);
+        for _ in 0..1000 {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let x = chunked.from_index((seed >> 33) as u32 % chunked.tile_count() as u32);
+            let before = components.components().count();
+
+            let event = if included[x] {
+                included[x] = false;
+                components.remove(x)
+            } else {
+                included[x] = true;
+                components.insert(x)
+            };
+
+            let after = components.components().count();
+            match event.unwrap() {
+                ComponentEvent::Created(_) => assert_eq!(after, before + 1),
+                ComponentEvent::Destroyed(_) => assert_eq!(after + 1, before),
+                ComponentEvent::Grown(_) | ComponentEvent::Shrunk(_) => assert_eq!(after, before),
+                ComponentEvent::Merged { from, .. } => {
+                    merges += 1;
+                    assert_eq!(after + from.len(), before);
+                }
+                ComponentEvent::Split { into, .. } => {
+                    splits += 1;
+                    assert_eq!(after, before + into.len());
+                }
+            }
+            check(&components, &included);
+        }
+
+        assert!(merges > 0 && splits > 0);
+        let present = chunked.iter_all().find(|&x| included[x]).unwrap();
+        assert_eq!(components.insert(present), None);
+    }
+
+    #[test]
+    fn edges_and_corners() {
+        use crate::edges::{CornerMap, EdgeMap};
+        use crate::{Corner, Edge};
+
+        for subdivisions in [0, 1, 4] {
+            let hsphere = Chunked::new(subdivisions);
+
+            let edges = hsphere.iter_edges().collect::<Vec<_>>();
+            let corners = hsphere.iter_corners().collect::<Vec<_>>();
+            assert_eq!(edges.len(), hsphere.edge_count());
+            assert_eq!(corners.len(), hsphere.corner_count());
+            assert_eq!(edges.iter().collect::<HashSet<_>>().len(), edges.len());
+            assert_eq!(corners.iter().collect::<HashSet<_>>().len(), corners.len());
+
+            for x in hsphere.iter_all() {
+                let around = hsphere.surrounding(x);
+                let tile_edges = hsphere.edges_around(x);
+                let tile_corners = hsphere.corners_around(x);
+                let positions = hsphere.corners_of(x);
+
+                for i in 0..around.len() {
+                    let edge = tile_edges[i];
+                    assert_eq!(edge, Edge::new(around[i], x));
+                    assert_eq!(edge.other(x), Some(around[i]));
+                    assert!(hsphere.is_edge(edge) && hsphere.is_corner(tile_corners[i]));
+                    assert!(hsphere
+                        .corner_position(tile_corners[i])
+                        .abs_diff_eq(positions[i], 1e-5));
+
+                    // Each edge runs between the corners on either side of it.
+                    let ends = hsphere.edge_corners(edge);
+                    let before = tile_corners[(i + around.len() - 1) % around.len()];
+                    assert!(ends.contains(&before) && ends.contains(&tile_corners[i]));
+                    assert!(tile_corners[i].edges().contains(&edge));
+                    assert!(hsphere.corner_neighbours(tile_corners[i]).contains(&before));
+                }
+            }
+
+            assert!(!hsphere.is_edge(Edge::new(Coordinate::Top, Coordinate::Bottom)));
+            assert!(!hsphere.is_corner(Corner::new(
+                Coordinate::Top,
+                Coordinate::Bottom,
+                hsphere.from_index(2)
+            )));
+
+            let mut lengths = EdgeMap::from_subdivisions(subdivisions, |edge| {
+                let [a, b] = edge.tiles();
+                hsphere.center_of(a).distance(hsphere.center_of(b))
+            });
+            let heights = CornerMap::from_subdivisions(subdivisions, |corner| corner);
+            assert!(edges.iter().zip(lengths.iter()).all(|(&a, (b, _))| a == b));
+            for &corner in &corners {
+                assert_eq!(heights[corner], corner);
+            }
+
+            let edge = edges[edges.len() / 2];
+            lengths[edge] = -1.0;
+            assert_eq!(lengths.get(edge), Some(&-1.0));
+            assert_eq!(lengths.all().filter(|&&x| x < 0.0).count(), 1);
+            assert_eq!(
+                lengths.get(Edge::new(Coordinate::Top, Coordinate::Bottom)),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn flat_shaded_dual() {
+        use crate::geometry_util::flat_dual;
+
+        let hsphere = Chunked::new(4);
+        let (_, dual, ()) = Hexasphere::make_dual(4, |_| (), |_, _, _, _, ()| ());
+        let (flat, tiles) = flat_dual(hsphere, 0.0);
+        let (inset, _) = flat_dual(hsphere, 0.25);
+
+        let corners = hsphere.iter_all().map(|x| hsphere.corners_of(x).len());
+        assert_eq!(flat.points.len(), corners.map(|n| n + 1).sum::<usize>());
+        assert_eq!(flat.indices.len(), dual.indices.len());
+        assert_eq!(tiles.len(), flat.points.len());
+        assert!(tiles.windows(2).all(|x| x[1] == x[0] || x[1] == x[0] + 1));
+
+        for triangle in flat.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+            // Every triangle is in one tile, and faces the same way as the tile.
+            assert!(tiles[a] == tiles[b] && tiles[b] == tiles[c]);
+            assert!(flat.normals[a] == flat.normals[b] && flat.normals[b] == flat.normals[c]);
+            let facing = (flat.points[b] - flat.points[a]).cross(flat.points[c] - flat.points[a]);
+            assert!(facing.dot(flat.normals[a]) > 0.0);
+
+            let x = hsphere.from_index(tiles[a]);
+            let center = hsphere.center_of(x);
+            assert!(flat.normals[a].dot(center) > 0.999);
+            assert!(hsphere
+                .corners_of(x)
+                .iter()
+                .any(|corner| corner.abs_diff_eq(flat.points[b], 1e-5)));
+            assert!(
+                (inset.points[b] - inset.points[a]).length()
+                    < (flat.points[b] - flat.points[a]).length()
+            );
+        }
+    }
+
+    #[test]
+    fn prism_terrain() {
+        use crate::geometry_util::{flat_dual, prisms};
+
+        let chunked = Chunked::new(4);
+        let (flat, _) = flat_dual(chunked, 0.0);
+        let (level, _) = prisms(&Hexasphere::from_subdivisions(4, |_| 0.0));
+        assert_eq!(level.points, flat.points);
+        assert_eq!(level.indices, flat.indices);
+
+        let heights = Hexasphere::from_subdivisions(4, |x| (chunked.to_index(x) % 4) as f32 * 0.05);
+        let (terrain, tiles) = prisms(&heights);
+        assert_eq!(tiles.len(), terrain.points.len());
+
+        let differing = chunked
+            .iter_all()
+            .flat_map(|x| chunked.surrounding(x).into_iter().map(move |y| (x, y)))
+            .filter(|&(x, y)| heights[x] > heights[y])
+            .count();
+        assert_eq!(terrain.indices.len(), flat.indices.len() + differing * 6);
+
+        for triangle in terrain.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+            let [pa, pb, pc] = [a, b, c].map(|i| terrain.points[i]);
+            assert!((pb - pa).cross(pc - pa).dot(terrain.normals[a]) > 0.0);
+            assert!(tiles[a] == tiles[b] && tiles[b] == tiles[c]);
+
+            // Nothing is higher than its own tile's top.
+            let height = heights[chunked.from_index(tiles[a])];
+            assert!([pa, pb, pc]
+                .iter()
+                .all(|p| p.length() <= 1.0 + height + 1e-5));
+        }
+    }
+
+    #[test]
+    fn geometry_uvs() {
+        use crate::geometry_util::flat_dual;
+
+        let (_, mut dual, ()) = Hexasphere::make_dual(4, |_| (), |_, _, _, _, ()| ());
+        let before = dual.points.len();
+        dual.tangents = vec![glam::Vec4::X; before];
+        let copied = dual.spherical_uvs();
+        assert_eq!(dual.points.len(), before + copied.len());
+        assert_eq!(dual.uvs.len(), dual.points.len());
+        assert!(dual.tangents.is_empty());
+        for (i, &from) in copied.iter().enumerate() {
+            assert_eq!(dual.points[before + i], dual.points[from as usize]);
+        }
+        assert!(dual
+            .uvs
+            .iter()
+            .all(|uv| (0.0..=1.5).contains(&uv.x) && (0.0..=1.0).contains(&uv.y)));
+        for triangle in dual.indices.chunks(3) {
+            let us = triangle.iter().map(|&i| dual.uvs[i as usize].x);
+            let (low, high) = us.fold((f32::MAX, f32::MIN), |(a, b), u| (a.min(u), b.max(u)));
+            assert!(high - low < 0.5);
+        }
+
+        let chunked = Chunked::new(4);
+        let (mut flat, tiles) = flat_dual(chunked, 0.0);
+        flat.tile_uvs(chunked, &tiles);
+        assert!(flat
+            .uvs
+            .iter()
+            .all(|uv| uv.cmpge(glam::Vec2::ZERO).all() && uv.cmple(glam::Vec2::ONE).all()));
+        // The middle of each tile is the middle of the texture.
+        for (i, &tile) in tiles.iter().enumerate() {
+            if flat.points[i].abs_diff_eq(chunked.center_of(chunked.from_index(tile)), 1e-5) {
+                assert!(flat.uvs[i].abs_diff_eq(glam::Vec2::splat(0.5), 1e-4));
+            }
+        }
+
+        #[cfg(feature = "mikktspace")]
+        {
+            assert!(flat.generate_tangents());
+            assert_eq!(flat.tangents.len(), flat.points.len());
+            for (tangent, normal) in flat.tangents.iter().zip(&flat.normals) {
+                assert!((tangent.truncate().length() - 1.0).abs() < 1e-3);
+                assert!(glam::Vec3A::from(tangent.truncate()).dot(*normal).abs() < 1e-3);
+                // `v` goes south, so the bitangent is mirrored from `normal × tangent`.
+                assert_eq!(tangent.w, -1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn obj_and_ply_export() {
+        use crate::export::{self, FaceTiles, Layer, PlyFormat};
+        use crate::geometry_util::flat_dual;
+
+        let (heights, dual, ()) = Hexasphere::make_dual(3, |_| (), |index, _, _, _, ()| index);
+        let faces = FaceTiles::dual(heights.chunked());
+        let triangles = dual.indices.len() / 3;
+
+        let mut obj = Vec::new();
+        export::write_obj_with_layer(
+            &dual,
+            &faces,
+            Layer {
+                name: "mid",
+                values: &heights,
+            },
+            &mut obj,
+        )
+        .unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        let count = |prefix: &str| obj.lines().filter(|x| x.starts_with(prefix)).count();
+        assert_eq!(count("v "), dual.points.len());
+        assert_eq!(count("vn "), dual.points.len());
+        assert_eq!(count("vt "), 0);
+        assert_eq!(count("f "), triangles);
+        assert_eq!(count("g "), heights.tile_count());
+        assert_eq!(count("# mid "), heights.tile_count());
+        assert!(obj.contains("g top\n# mid 0\nf 1//1 "));
+
+        // Each tile's triangles fan out from its center, which `make_dual` hands over.
+        for (face, triangle) in dual.indices.chunks(3).enumerate() {
+            let x = heights.from_index(faces.tiles()[face]);
+            assert_eq!(triangle[0], heights[x]);
+        }
+
+        let mut ascii = Vec::new();
+        export::write_ply(&dual, Some(&faces), PlyFormat::Ascii, &mut ascii).unwrap();
+        let ascii = String::from_utf8(ascii).unwrap();
+        let (header, body) = ascii.split_once("end_header\n").unwrap();
+        assert!(header.contains(&format!("element face {triangles}\n")));
+        let rows = body.lines().collect::<Vec<_>>();
+        assert_eq!(rows.len(), dual.points.len() + triangles);
+        let last = rows.last().unwrap().split(' ').collect::<Vec<_>>();
+        let Coordinate::Inside { chunk, short, long } =
+            heights.from_index(*faces.tiles().last().unwrap())
+        else {
+            panic!("The last tile is a pole!");
+        };
+        assert_eq!(
+            last[4..],
+            [
+                faces.tiles().last().unwrap().to_string(),
+                chunk.to_string(),
+                short.to_string(),
+                long.to_string()
+            ]
+        );
+
+        // Binary takes 6 floats per point, and 1 + 3 + 4 values per face.
+        let chunked = Chunked::new(3);
+        let (mut flat, points) = flat_dual(chunked, 0.0);
+        flat.tile_uvs(chunked, &points);
+        let faces = FaceTiles::from_points(chunked, &flat, &points);
+        let colours = Hexasphere::from_subdivisions(3, |_| [0.5f32; 3]);
+        let mut binary = Vec::new();
+        export::write_ply_with_layer(
+            &flat,
+            &faces,
+            Layer {
+                name: "colour",
+                values: &colours,
+            },
+            PlyFormat::BinaryLittleEndian,
+            &mut binary,
+        )
+        .unwrap();
+        let start = binary
+            .windows(11)
+            .position(|x| x == b"end_header\n")
+            .unwrap()
+            + 11;
+        let header = std::str::from_utf8(&binary[..start]).unwrap();
+        assert!(header.contains("format binary_little_endian 1.0"));
+        assert!(header.contains("property float s\n"));
+        assert!(header.contains("property float colour_2\n"));
+        let triangles = flat.indices.len() / 3;
+        assert_eq!(
+            binary.len() - start,
+            flat.points.len() * 8 * 4 + triangles * (1 + 3 * 4 + 4 + 1 + 4 + 4 + 3 * 4)
+        );
+
+        // Both formats flip `v` the same way.
+        let mut obj = Vec::new();
+        export::write_obj(&flat, None, &mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        let from_obj = obj
+            .lines()
+            .filter_map(|x| x.strip_prefix("vt "))
+            .map(|x| x.to_owned())
+            .collect::<Vec<_>>();
+        let mut ascii = Vec::new();
+        export::write_ply(&flat, None, PlyFormat::Ascii, &mut ascii).unwrap();
+        let ascii = String::from_utf8(ascii).unwrap();
+        let from_ply = ascii
+            .split_once("end_header\n")
+            .unwrap()
+            .1
+            .lines()
+            .take(flat.points.len())
+            .map(|x| x.split(' ').skip(6).collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>();
+        assert_eq!(from_obj.len(), flat.points.len());
+        assert_eq!(from_ply, from_obj);
+        assert!(from_obj.contains(&format!("{} {}", flat.uvs[0].x, 1.0 - flat.uvs[0].y)));
+
+        let wrong = FaceTiles::dual(Chunked::new(2));
+        assert!(export::write_obj(&flat, Some(&wrong), std::io::sink()).is_err());
+
+        let uv = flat.uvs.pop().unwrap();
+        let error = export::write_obj(&flat, None, std::io::sink()).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        let error = export::write_ply(&flat, None, PlyFormat::Ascii, std::io::sink());
+        assert_eq!(error.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+        flat.uvs.push(uv);
+
+        flat.normals.pop();
+        assert!(export::write_obj(&flat, Some(&faces), std::io::sink()).is_err());
+        assert!(export::write_ply(&flat, None, PlyFormat::Ascii, std::io::sink()).is_err());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_matches_serial() {
+        for subdivisions in [0, 1, 5] {
+            let (serial, serial_geometry, ()) = Hexasphere::make_dual(
+                subdivisions,
+                |_| (),
+                |index, edges, coord, _, ()| (index, edges, coord),
+            );
+            let (parallel, parallel_geometry) =
+                Hexasphere::par_make_dual(subdivisions, |index, edges, coord, _| {
+                    (index, edges, coord)
+                });
+
+            assert_eq!(parallel, serial);
+            assert_eq!(parallel_geometry.points, serial_geometry.points);
+            assert_eq!(parallel_geometry.normals, serial_geometry.normals);
+            assert_eq!(parallel_geometry.indices, serial_geometry.indices);
+
+            let hsphere = parallel.chunked();
+            let (indices, old) = native_indices(hsphere);
+            let (serial, store) =
+                Hexasphere::from_hexasphere_geometry(subdivisions, &indices, |i, x| (i, x));
+            let parallel =
+                Hexasphere::par_make_from_surrounding(subdivisions, &store, |i, x| (i, x));
+            assert_eq!(parallel, serial);
+            assert_eq!(
+                Adjacency::par_from_indices(&indices).unwrap(),
+                Adjacency::from_indices(&indices).unwrap()
+            );
+
+            let mut points = vec![Vec3A::ZERO; hsphere.tile_count()];
+            for x in hsphere.iter_all() {
+                points[old(x) as usize] = hsphere.center_of(x);
+            }
+            let (serial, serial_geometry, ()) = Hexasphere::make_and_dual(
+                subdivisions,
+                &indices,
+                &points,
+                |_| (),
+                |index, edges, coord, _, ()| (index, edges, coord),
+            );
+            let (parallel, parallel_geometry) = Hexasphere::par_make_and_dual(
+                subdivisions,
+                &indices,
+                &points,
+                |index, edges, coord, _| (index, edges, coord),
+            );
+            assert_eq!(parallel, serial);
+            assert_eq!(parallel_geometry.points, serial_geometry.points);
+            assert_eq!(parallel_geometry.indices, serial_geometry.indices);
+            assert_eq!(
+                Hexasphere::par_from_subdivisions(subdivisions, |x| x),
+                Hexasphere::from_subdivisions(subdivisions, |x| x)
+            );
+        }
+    }
+
+    #[test]
+    fn spans_record_subdivisions() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id};
+        use tracing_subscriber::layer::{Context, SubscriberExt};
+        use tracing_subscriber::Layer;
+
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<(&'static str, u64)>>>);
+
+        struct Subdivisions(Option<u64>);
+
+        impl Visit for Subdivisions {
+            fn record_u64(&mut self, field: &Field, value: u64) {
+                if field.name() == "subdivisions" {
+                    self.0 = Some(value);
+                }
+            }
+
+            fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+        }
+
+        impl<S: tracing::Subscriber> Layer<S> for Capture {
+            fn on_new_span(&self, attrs: &Attributes<'_>, _: &Id, _: Context<'_, S>) {
+                let mut visitor = Subdivisions(None);
+                attrs.record(&mut visitor);
+                if let Some(subdivisions) = visitor.0 {
+                    let name = attrs.metadata().name();
+                    self.0.lock().unwrap().push((name, subdivisions));
+                }
+            }
+        }
+
+        let hsphere = Chunked::new(2);
+        let (indices, old) = native_indices(hsphere);
+        let mut points = vec![glam::Vec3A::ZERO; hsphere.tile_count()];
+        for x in hsphere.iter_all() {
+            points[old(x) as usize] = hsphere.center_of(x);
+        }
+
+        let capture = Capture::default();
+        let subscriber = tracing_subscriber::registry().with(capture.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            Hexasphere::make_dual(3, |_| (), |_, _, _, _, ()| ());
+            Hexasphere::make_and_dual(2, &indices, &points, |_| (), |_, _, _, _, ()| ());
+            #[cfg(feature = "rayon")]
+            Hexasphere::par_make_dual(1, |_, _, _, _| ());
+        });
+
+        let mut expected = vec![
+            ("make_dual", 3),
+            ("try_make_and_dual", 2),
+            ("try_make_from_surrounding", 2),
+        ];
+        if cfg!(feature = "rayon") {
+            expected.push(("par_make_dual", 1));
+        }
+
+        let spans = capture.0.lock().unwrap();
+        for (name, subdivisions) in expected {
+            assert!(
+                spans.contains(&(name, subdivisions)),
+                "{name} didn't record {subdivisions} subdivisions in {spans:?}"
+            );
+        }
+    }
 }
@@ -0,0 +1,478 @@
+//! The five chunks unfolded into the plane.
+//!
+//! Inside a chunk, `(short, long)` are axial coordinates on a hex lattice
+//! whose six directions are `(±1, 0)`, `(0, ±1)` and `±(1, -1)`. Each chunk
+//! covers four faces of the icosahedron, which unfold into the parallelogram
+//! `short in -1..=n - 1`, `long in 0..=2 * n` where `n = subdivisions + 1`.
+//! `Top` sits at `(-1, 0)` and `Bottom` at `(n - 1, 2 * n)` in every chunk.
+//!
+//! Chunk `c + 1` is glued onto chunk `c` along three seams, and walking
+//! those seams lets us lay out as much of the sphere in one plane as we
+//! need, with plain hex distances measuring hops.
+
+use crate::{coord, Coordinate};
+use glam::Vec3A;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+pub(crate) type Point = [i64; 2];
+
+/// How many seams may be crossed when laying out candidate positions of a
+/// target tile. Shortest paths never wind further than this around the net.
+const MAX_SEAMS: usize = 3;
+
+/// Rotates by 60 degrees counter-clockwise, `by` times.
+fn rotate([q, r]: Point, by: u8) -> Point {
+    (0..by % 6).fold([q, r], |[q, r], _| [-r, q + r])
+}
+
+/// Number of hops between two lattice points on a flat hex grid.
+pub(crate) fn hex_len([q, r]: Point) -> i64 {
+    (q.abs() + r.abs() + (q + r).abs()) / 2
+}
+
+/// Maps points from a chunk's own frame into some shared frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Placement {
+    rotation: u8,
+    offset: Point,
+}
+
+impl Placement {
+    pub(crate) const IDENTITY: Self = Self {
+        rotation: 0,
+        offset: [0, 0],
+    };
+
+    pub(crate) fn apply(self, p: Point) -> Point {
+        let [q, r] = rotate(p, self.rotation);
+        [q + self.offset[0], r + self.offset[1]]
+    }
+
+    /// Applies `inner` first, then `self`.
+    fn then(self, inner: Self) -> Self {
+        Self {
+            rotation: (self.rotation + inner.rotation) % 6,
+            offset: self.apply(inner.offset),
+        }
+    }
+
+    pub(crate) fn inverse(self) -> Self {
+        let rotation = (6 - self.rotation) % 6;
+        let [q, r] = rotate(self.offset, rotation);
+        Self {
+            rotation,
+            offset: [-q, -r],
+        }
+    }
+}
+
+/// The edges along which chunk `c + 1` is glued onto chunk `c`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Seam {
+    /// Chunk `c`'s `short == -1` edge above `long == n`, turning about `Top`.
+    Top,
+    /// Chunk `c`'s `short == -1` edge below `long == n`.
+    Middle,
+    /// Chunk `c`'s `long == 2 * n` edge, turning about `Bottom`.
+    Bottom,
+}
+
+impl Seam {
+    const ALL: [Self; 3] = [Self::Top, Self::Middle, Self::Bottom];
+
+    /// Maps the frame of chunk `c + 1` into the frame of chunk `c`.
+    fn placement(self, n: i64) -> Placement {
+        match self {
+            Seam::Top => Placement {
+                rotation: 1,
+                offset: [-1, 1],
+            },
+            Seam::Middle => Placement {
+                rotation: 0,
+                offset: [-n, n],
+            },
+            Seam::Bottom => Placement {
+                rotation: 5,
+                offset: [-2 * n, 3 * n - 1],
+            },
+        }
+    }
+
+    /// Crosses this seam from `chunk`, forwards to `chunk + 1` or backwards
+    /// to `chunk - 1`.
+    fn cross(self, n: i64, chunk: u8, placement: Placement, forwards: bool) -> (u8, Placement) {
+        if forwards {
+            ((chunk + 1) % 5, placement.then(self.placement(n)))
+        } else {
+            ((chunk + 4) % 5, placement.then(self.placement(n).inverse()))
+        }
+    }
+}
+
+/// A chunk laid out in the shared frame.
+type Strip = (u8, Placement);
+
+/// A vertex of the icosahedron on the ring above (`y > 0`) or below the
+/// equator, `angle` degrees around from `+x` towards `+z`.
+fn ring_vertex(angle: f32, y: f32) -> Vec3A {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let radius = 2.0 * y.abs();
+    Vec3A::new(radius * cos, y, radius * sin)
+}
+
+/// The corners of the two rhombi (each a pair of icosahedron faces) making
+/// up a chunk, in the order `(0, 0)`, `(n, 0)`, `(0, n)`, `(n, n)` of
+/// `(short + 1, long)` relative to the rhombus.
+///
+/// This matches the layout of the `hexasphere` crate's icosahedron, with
+/// chunk `c` starting out from `Top` towards its vertex `5 - c`.
+fn rhombi(chunk: u8) -> [[Vec3A; 4]; 2] {
+    let y = 5.0f32.sqrt().recip();
+    let upper = |c: u8| ring_vertex(-72.0 * f32::from(c + 1), y);
+    let lower = |c: u8| ring_vertex(-72.0 * f32::from(c) - 108.0, -y);
+    let next = (chunk + 1) % 5;
+
+    [
+        [Vec3A::Y, upper(chunk), upper(next), lower(chunk)],
+        [upper(next), lower(chunk), lower(next), Vec3A::NEG_Y],
+    ]
+}
+
+/// Spherical interpolation between two unit vectors.
+fn slerp(a: Vec3A, b: Vec3A, t: f32) -> Vec3A {
+    let angle = a.angle_between(b);
+    if angle < f32::EPSILON {
+        return a;
+    }
+
+    (a * ((1.0 - t) * angle).sin() + b * (t * angle).sin()) / angle.sin()
+}
+
+/// Position on the unit sphere of a (possibly fractional) point in
+/// `chunk`'s frame.
+///
+/// Like the `hexasphere` crate, this interpolates spherically: first
+/// along the two edges leaving a face's corner, then across between them.
+pub(crate) fn to_sphere(n: i64, chunk: u8, [short, long]: [f32; 2]) -> Vec3A {
+    let n = n as f32;
+    let u = (short + 1.0) / n;
+    let v = long / n;
+    let (half, v) = if v <= 1.0 { (0, v) } else { (1, v - 1.0) };
+    let [a, x, y, z] = rhombi(chunk)[half];
+
+    // Distance from the corner we're interpolating out of, how far along
+    // towards its second edge we are, and its two edges.
+    let (k, t, from, to) = if u + v <= 1.0 {
+        (u + v, v, [a, a], [x, y])
+    } else {
+        (2.0 - u - v, 1.0 - v, [z, z], [y, x])
+    };
+
+    if k < f32::EPSILON {
+        return from[0];
+    }
+
+    slerp(slerp(from[0], to[0], k), slerp(from[1], to[1], k), t / k)
+}
+
+/// Rounds a fractional point to the nearest lattice point.
+fn round([q, r]: [f32; 2]) -> Point {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+
+    [rq as i64, rr as i64]
+}
+
+/// A tile near where `direction` points.
+///
+/// This finds the icosahedron face `direction` passes through and
+/// places it on that face by projecting onto the face's plane. That's not
+/// quite the inverse of [`to_sphere`], so this may be a step or so off.
+pub(crate) fn from_sphere(n: i64, direction: Vec3A) -> Coordinate {
+    let faces = (0..5u8).flat_map(|chunk| {
+        let rhombi = rhombi(chunk);
+        [0, 1].into_iter().flat_map(move |half| {
+            let [a, x, y, z] = rhombi[half];
+            [
+                (chunk, half, false, [a, x, y]),
+                (chunk, half, true, [z, y, x]),
+            ]
+        })
+    });
+
+    let (chunk, half, flipped, [a, x, y]) = faces
+        .max_by(|(.., p), (.., q)| {
+            let p = direction.dot(p[0] + p[1] + p[2]);
+            let q = direction.dot(q[0] + q[1] + q[2]);
+            p.total_cmp(&q)
+        })
+        .unwrap();
+
+    let normal = (x - a).cross(y - a);
+    let on_plane = direction * (a.dot(normal) / direction.dot(normal)) - a;
+
+    // Solve `on_plane = alpha * (x - a) + beta * (y - a)`.
+    let (ex, ey) = (x - a, y - a);
+    let (xx, xy, yy) = (ex.dot(ex), ex.dot(ey), ey.dot(ey));
+    let (px, py) = (on_plane.dot(ex), on_plane.dot(ey));
+    let det = xx * yy - xy * xy;
+    let alpha = (px * yy - py * xy) / det;
+    let beta = (py * xx - px * xy) / det;
+
+    let (u, v) = if flipped {
+        (1.0 - alpha, 1.0 - beta)
+    } else {
+        (alpha, beta)
+    };
+
+    let scale = n as f32;
+    let [short, long] = round([u * scale - 1.0, (v + half as f32) * scale]);
+    tile_at(n, chunk, [short.clamp(-1, n - 1), long.clamp(0, 2 * n)])
+}
+
+/// Where a coordinate lives: its chunk (or `None` for the poles, which
+/// belong to all of them) and its position in that chunk's frame.
+pub(crate) fn locate(n: i64, coordinate: Coordinate) -> (Option<u8>, Point) {
+    match coordinate {
+        Coordinate::Top => (None, [-1, 0]),
+        Coordinate::Bottom => (None, [n - 1, 2 * n]),
+        Coordinate::Inside { chunk, short, long } => (Some(chunk), [short as i64, long as i64]),
+    }
+}
+
+/// The tile at a lattice point on the closed parallelogram of `chunk`.
+fn tile_at(n: i64, chunk: u8, p: Point) -> Coordinate {
+    let owner = |seam: Seam| {
+        let [short, long] = seam.placement(n).inverse().apply(p);
+        coord((chunk + 1) % 5, short as usize, long as usize)
+    };
+
+    match p {
+        [-1, 0] => Coordinate::Top,
+        [q, r] if q == n - 1 && r == 2 * n => Coordinate::Bottom,
+        [-1, r] if r <= n => owner(Seam::Top),
+        [-1, _] => owner(Seam::Middle),
+        [_, r] if r == 2 * n => owner(Seam::Bottom),
+        [q, r] => coord(chunk, q as usize, r as usize),
+    }
+}
+
+/// Straight segment from `from` by `delta`, tracked across seams.
+///
+/// Positions along the segment are `s / scale` with `s in 0..=scale`, where
+/// `scale` is picked so every crossing with a chunk edge lands on an integer.
+struct Walk {
+    n: i64,
+    from: Point,
+    delta: Point,
+    scale: i64,
+    target: Coordinate,
+}
+
+impl Walk {
+    fn new(n: i64, from: Point, to: Point, target: Coordinate) -> Self {
+        let delta = [to[0] - from[0], to[1] - from[1]];
+        let scale = [delta[0], delta[1], delta[0] + delta[1]]
+            .into_iter()
+            .filter(|&x| x != 0)
+            .map(i64::abs)
+            .product();
+
+        Self {
+            n,
+            from,
+            delta,
+            scale,
+            target,
+        }
+    }
+
+    /// The segment in the strip's frame: scaled start and direction.
+    fn local(&self, (_, placement): Strip) -> (Point, Point) {
+        let inverse = placement.inverse();
+        let [q, r] = inverse.apply(self.from);
+        (
+            [q * self.scale, r * self.scale],
+            rotate(self.delta, inverse.rotation),
+        )
+    }
+
+    /// The range of `s` for which the segment lies inside the strip.
+    fn clip(&self, strip: Strip) -> Option<(i64, i64)> {
+        let (start, dir) = self.local(strip);
+        let bounds = [[-1, self.n - 1], [0, 2 * self.n]];
+
+        let mut range = (0, self.scale);
+        for axis in 0..2 {
+            let [lo, hi] = bounds[axis].map(|x| x * self.scale);
+            if dir[axis] == 0 {
+                if start[axis] < lo || start[axis] > hi {
+                    return None;
+                }
+                continue;
+            }
+
+            let (a, b) = (
+                (lo - start[axis]) / dir[axis],
+                (hi - start[axis]) / dir[axis],
+            );
+            range = (range.0.max(a.min(b)), range.1.min(a.max(b)));
+        }
+
+        (range.0 <= range.1).then_some(range)
+    }
+
+    /// Strips other than `strip` which touch the point at `s`, found by
+    /// crossing every seam passing through it.
+    fn around(&self, strip: Strip, s: i64) -> Vec<Strip> {
+        let mut found = vec![strip];
+        let mut next = 0;
+
+        while next < found.len() && found.len() < 12 {
+            let current = found[next];
+            next += 1;
+
+            let (start, dir) = self.local(current);
+            let [q, r] = [start[0] + s * dir[0], start[1] + s * dir[1]];
+            let (n, scale) = (self.n, self.scale);
+
+            let on_left = q == -scale;
+            let on_right = q == (n - 1) * scale;
+            let upper = (0..=n * scale).contains(&r);
+            let lower = (n * scale..=2 * n * scale).contains(&r);
+
+            let mut crossings = Vec::new();
+            if on_left && upper {
+                crossings.push((Seam::Top, true));
+            }
+            if on_left && lower {
+                crossings.push((Seam::Middle, true));
+            }
+            if on_right && upper {
+                crossings.push((Seam::Middle, false));
+            }
+            if on_right && lower {
+                crossings.push((Seam::Bottom, false));
+            }
+            if r == 0 {
+                crossings.push((Seam::Top, false));
+            }
+            if r == 2 * n * scale {
+                crossings.push((Seam::Bottom, true));
+            }
+
+            for (seam, forwards) in crossings {
+                let neighbour = seam.cross(n, current.0, current.1, forwards);
+                if !found.contains(&neighbour) {
+                    found.push(neighbour);
+                }
+            }
+        }
+
+        found.remove(0);
+        found
+    }
+
+    /// Follows the segment from `s` onwards, starting in `strip`, and
+    /// reports whether it ends on the target tile.
+    fn follow(&self, strip: Strip, s: i64) -> bool {
+        let Some((lo, hi)) = self.clip(strip) else {
+            return false;
+        };
+
+        if lo > s {
+            return false;
+        }
+
+        if hi == self.scale {
+            let end = [self.from[0] + self.delta[0], self.from[1] + self.delta[1]];
+            return tile_at(self.n, strip.0, strip.1.inverse().apply(end)) == self.target;
+        }
+
+        self.around(strip, hi).into_iter().any(|neighbour| {
+            self.clip(neighbour)
+                .is_some_and(|(lo, next)| lo <= hi && next > hi)
+                && self.follow(neighbour, hi)
+        })
+    }
+}
+
+/// Every way of laying out chunks within [`MAX_SEAMS`] crossings of chunk
+/// `0`, as placements for `n = 0` and `n = 1`. Offsets are linear in `n`,
+/// so these are found once and interpolated for any other size.
+fn layouts() -> &'static [(u8, Placement, Placement)] {
+    static LAYOUTS: OnceLock<Vec<(u8, Placement, Placement)>> = OnceLock::new();
+
+    LAYOUTS.get_or_init(|| {
+        let start = (0, Placement::IDENTITY, Placement::IDENTITY);
+        let mut seen = HashSet::from([start]);
+        let mut layer = vec![start];
+
+        for _ in 0..MAX_SEAMS {
+            let mut next = Vec::new();
+            for (chunk, at_zero, at_one) in layer {
+                for seam in Seam::ALL {
+                    for forwards in [true, false] {
+                        let (next_chunk, at_zero) = seam.cross(0, chunk, at_zero, forwards);
+                        let (_, at_one) = seam.cross(1, chunk, at_one, forwards);
+                        let layout = (next_chunk, at_zero, at_one);
+                        if seen.insert(layout) {
+                            next.push(layout);
+                        }
+                    }
+                }
+            }
+            layer = next;
+        }
+
+        seen.into_iter().collect()
+    })
+}
+
+/// Every way of laying out chunks within [`MAX_SEAMS`] crossings of `start`.
+fn strips(n: i64, start: u8) -> impl Iterator<Item = Strip> {
+    layouts().iter().map(move |&(chunk, at_zero, at_one)| {
+        let offset = [0, 1].map(|i| at_zero.offset[i] + n * (at_one.offset[i] - at_zero.offset[i]));
+        (
+            (chunk + start) % 5,
+            Placement {
+                rotation: at_zero.rotation,
+                offset,
+            },
+        )
+    })
+}
+
+/// Exact number of hops between two coordinates.
+///
+/// Candidate positions of `b` are laid out around `a`'s chunk, and the
+/// nearest one whose straight line from `a` really crosses the seams the
+/// layout assumes gives the distance.
+pub(crate) fn distance(subdivisions: usize, a: Coordinate, b: Coordinate) -> usize {
+    let n = subdivisions as i64 + 1;
+    let (a_chunk, a_point) = locate(n, a);
+    let (b_chunk, b_point) = locate(n, b);
+    let start = a_chunk.unwrap_or(0);
+
+    let mut candidates = strips(n, start)
+        .filter(|&(chunk, _)| b_chunk.map_or(true, |x| x == chunk))
+        .map(|(_, placement)| placement.apply(b_point))
+        .map(|p| (hex_len([p[0] - a_point[0], p[1] - a_point[1]]), p))
+        .collect::<Vec<_>>();
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    candidates
+        .into_iter()
+        .find(|&(_, p)| Walk::new(n, a_point, p, b).follow((start, Placement::IDENTITY), 0))
+        .map(|(len, _)| len as usize)
+        .expect("some layout of the net always reaches the target")
+}
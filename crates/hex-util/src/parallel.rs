@@ -0,0 +1,208 @@
+//! Parallel versions of the constructors, behind the `rayon` feature.
+//!
+//! Everything here produces exactly what its serial counterpart does,
+//! so switching between the two never changes a generated world.
+
+use crate::adjacency::{Adjacency, Neighbours};
+use crate::geometry_util::{self, GeometryData};
+use crate::{
+    check_poles, coord, walk_chunk, Chunked, Coordinate, Error, Hexagonish, Hexasphere, Result,
+};
+use glam::Vec3A;
+use rayon::prelude::*;
+use tracing::instrument;
+
+impl<T: Send> Hexasphere<T> {
+    /// Like [`Hexasphere::from_subdivisions`], but calls `make` from many threads.
+    pub fn par_from_subdivisions(
+        subdivisions: usize,
+        make: impl Fn(Coordinate) -> T + Sync,
+    ) -> Self {
+        let inner = Chunked { subdivisions };
+        let row = 2 * (subdivisions + 1);
+        let chunks = [0, 1, 2, 3, 4].map(|chunk| {
+            (0..inner.chunk_len())
+                .into_par_iter()
+                .map(|i| make(coord(chunk, i / row, i % row)))
+                .collect::<Vec<_>>()
+        });
+
+        Self {
+            inner,
+            top: make(Coordinate::Top),
+            bottom: make(Coordinate::Bottom),
+            chunks,
+            neighbours: None,
+        }
+    }
+
+    /// Like [`Hexasphere::make_from_surrounding`], but walks the chunks in parallel.
+    ///
+    /// # Panics
+    /// If `coordinate_store` is malformed; see [`Hexasphere::try_par_make_from_surrounding`].
+    pub fn par_make_from_surrounding(
+        subdivisions: usize,
+        coordinate_store: &(impl Neighbours + Sync),
+        make: impl Fn(u32, Coordinate) -> T + Sync,
+    ) -> Self {
+        Self::try_par_make_from_surrounding(subdivisions, coordinate_store, make)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`Hexasphere::try_make_from_surrounding`], but walks the chunks in parallel.
+    ///
+    /// # Errors
+    /// As in [`Hexasphere::try_make_from_surrounding`], although
+    /// which error is found first may differ, and `make` may
+    /// have been called more than once for an old index by then.
+    pub fn try_par_make_from_surrounding(
+        subdivisions: usize,
+        coordinate_store: &(impl Neighbours + Sync),
+        make: impl Fn(u32, Coordinate) -> T + Sync,
+    ) -> Result<Self> {
+        Self::try_par_make_from_surrounding_with(subdivisions, coordinate_store, |old, coord| {
+            Ok(make(old, coord))
+        })
+    }
+
+    /// [`Hexasphere::try_par_make_from_surrounding`], but `make` can fail too.
+    #[instrument(
+        name = "try_par_make_from_surrounding",
+        skip_all,
+        fields(subdivisions = subdivisions, tiles = coordinate_store.vertex_count())
+    )]
+    fn try_par_make_from_surrounding_with(
+        subdivisions: usize,
+        coordinate_store: &(impl Neighbours + Sync),
+        make: impl Fn(u32, Coordinate) -> Result<T> + Sync,
+    ) -> Result<Self> {
+        check_poles(coordinate_store)?;
+
+        let chunks = (0..5u8)
+            .into_par_iter()
+            .map(|chunk| {
+                let mut seen = Vec::new();
+                let data = walk_chunk(subdivisions, coordinate_store, chunk, |old, coord| {
+                    seen.push(old);
+                    make(old, coord)
+                })?;
+                Ok((data, seen))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // The serial walk notices this as it goes, but here
+        // each chunk only knows what it has walked over itself.
+        let mut seen = chunks
+            .iter()
+            .flat_map(|(_, seen)| seen)
+            .copied()
+            .chain([0, 11])
+            .collect::<Vec<_>>();
+        seen.par_sort_unstable();
+        if let Some(x) = seen.windows(2).find(|x| x[0] == x[1]) {
+            return Err(Error::InconsistentWinding { vertex: x[0] });
+        }
+
+        let top = make(0, Coordinate::Top)?;
+        let bottom = make(11, Coordinate::Bottom)?;
+        let chunks = chunks.into_iter().map(|(data, _)| data).collect::<Vec<_>>();
+
+        Ok(Self {
+            inner: Chunked { subdivisions },
+            top,
+            bottom,
+            chunks: chunks.try_into().unwrap_or_else(|_| unreachable!()),
+            neighbours: None,
+        })
+    }
+
+    /// Like [`Hexasphere::make_dual`], but builds the geometry
+    /// and calls `make` from many threads.
+    ///
+    /// Since `make` can't modify the geometry or share temporary data
+    /// here, it only gets to read the finished geometry.
+    #[instrument(skip_all, fields(subdivisions = subdivisions, tiles = Chunked { subdivisions }.tile_count()))]
+    pub fn par_make_dual(
+        subdivisions: usize,
+        make: impl Fn(u32, Hexagonish<u32>, Coordinate, &GeometryData) -> T + Sync,
+    ) -> (Self, GeometryData) {
+        let chunked = Chunked { subdivisions };
+        let count = chunked.tile_count() as u32;
+        let centers = (0..count)
+            .into_par_iter()
+            .map(|i| chunked.center_of(chunked.from_index(i)))
+            .collect::<Vec<_>>();
+        let surrounding = (0..count)
+            .into_par_iter()
+            .map(|i| {
+                let around = chunked.surrounding(chunked.from_index(i)).into_iter();
+                around
+                    .map(|y| chunked.to_index(y))
+                    .collect::<Hexagonish<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let (dual_data, convert_to_dual_space) =
+            geometry_util::dense_dual(&centers, &Adjacency::from_lists(surrounding), true)
+                .unwrap_or_else(|e| unreachable!("{e}"));
+
+        let sphere = Self::par_from_subdivisions(subdivisions, |coord| {
+            let (new, edges) = convert_to_dual_space[chunked.to_index(coord) as usize]
+                .clone()
+                .unwrap_or_else(|| unreachable!());
+            make(new, edges, coord, &dual_data)
+        });
+
+        (sphere, dual_data)
+    }
+
+    /// Like [`Hexasphere::make_and_dual`], but builds the adjacency
+    /// map and the geometry, and calls `make`, from many threads.
+    ///
+    /// As in [`Hexasphere::par_make_dual`], `make` only gets
+    /// to read the finished geometry.
+    ///
+    /// # Panics
+    /// If the geometry is malformed; see [`Hexasphere::try_par_make_and_dual`].
+    pub fn par_make_and_dual(
+        subdivisions: usize,
+        indices: &[u32],
+        ico_points: &[Vec3A],
+        make: impl Fn(u32, Hexagonish<u32>, Coordinate, &GeometryData) -> T + Sync,
+    ) -> (Self, GeometryData) {
+        Self::try_par_make_and_dual(subdivisions, indices, ico_points, make)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like [`Hexasphere::try_make_and_dual`], but builds the adjacency
+    /// map and the geometry, and calls `make`, from many threads.
+    ///
+    /// # Errors
+    /// As in [`Hexasphere::try_make_and_dual`], although
+    /// which error is found first may differ.
+    #[instrument(skip_all, fields(subdivisions = subdivisions, tiles = ico_points.len()))]
+    pub fn try_par_make_and_dual(
+        subdivisions: usize,
+        indices: &[u32],
+        ico_points: &[Vec3A],
+        make: impl Fn(u32, Hexagonish<u32>, Coordinate, &GeometryData) -> T + Sync,
+    ) -> Result<(Self, GeometryData)> {
+        let coordinate_store = Adjacency::par_from_indices(indices)?;
+        let (dual_data, convert_to_dual_space) =
+            geometry_util::dense_dual(ico_points, &coordinate_store, true)?;
+
+        let sphere = Self::try_par_make_from_surrounding_with(
+            subdivisions,
+            &coordinate_store,
+            |old, coord| {
+                let (new, edges) = convert_to_dual_space
+                    .get(old as usize)
+                    .and_then(Option::as_ref)
+                    .ok_or(Error::MissingVertex { vertex: old })?;
+                Ok(make(*new, edges.clone(), coord, &dual_data))
+            },
+        )?;
+
+        Ok((sphere, dual_data))
+    }
+}
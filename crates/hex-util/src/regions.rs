@@ -0,0 +1,393 @@
+//! Connected regions of tiles: labelled regions and how they border each other,
+//! and components of a set of tiles kept up to date as it changes.
+
+use crate::{Blob, Coordinate, Hexasphere};
+use std::collections::{HashMap, HashSet};
+
+/// One connected area of tiles which all have the same label.
+#[derive(Debug)]
+pub struct Region<L> {
+    pub label: L,
+    /// The tiles of the region, where the borders
+    /// are those next to a different region.
+    pub blob: Blob,
+}
+
+/// Every [`Region`] of a `Hexasphere` of labels, such as biomes or
+/// owners, along with which regions touch and along how many edges.
+///
+/// Regions are numbered in the order [`crate::Chunked::iter_all`]
+/// first reaches them, so the same labels always give the same ids.
+#[derive(Debug)]
+pub struct Regions<L> {
+    all: Vec<Region<L>>,
+    ids: Hexasphere<u32>,
+    /// For each region, the regions it touches by id
+    /// and the number of tile edges they share.
+    adjacent: Vec<Vec<(u32, usize)>>,
+}
+
+impl<L: Clone + PartialEq> Regions<L> {
+    pub fn new(labels: &Hexasphere<L>) -> Self {
+        let mut ids = labels.change_type(|_| u32::MAX);
+        let mut regions = Vec::new();
+
+        for start in labels.iter_all() {
+            if ids[start] != u32::MAX {
+                continue;
+            }
+
+            let id = regions.len() as u32;
+            let label = &labels[start];
+            let mut contents = HashSet::from([start]);
+            let mut borders = Vec::new();
+            let mut to_explore = vec![start];
+            ids[start] = id;
+
+            while let Some(next) = to_explore.pop() {
+                let mut bordered = false;
+                for around in labels.surrounding(next) {
+                    if labels[around] != *label {
+                        bordered = true;
+                    } else if ids[around] == u32::MAX {
+                        ids[around] = id;
+                        contents.insert(around);
+                        to_explore.push(around);
+                    }
+                }
+
+                if bordered {
+                    borders.push(next);
+                }
+            }
+
+            regions.push(Region {
+                label: label.clone(),
+                blob: Blob { contents, borders },
+            });
+        }
+
+        let mut shared = HashMap::<(u32, u32), usize>::new();
+        for region in &regions {
+            for &x in &region.blob.borders {
+                for around in labels.surrounding(x) {
+                    let (a, b) = (ids[x], ids[around]);
+                    // Count each edge once, from its lower side.
+                    if a < b {
+                        *shared.entry((a, b)).or_default() += 1;
+                    }
+                }
+            }
+        }
+
+        let mut adjacent = vec![Vec::new(); regions.len()];
+        for (&(a, b), &edges) in &shared {
+            adjacent[a as usize].push((b, edges));
+            adjacent[b as usize].push((a, edges));
+        }
+        for list in &mut adjacent {
+            list.sort_unstable();
+        }
+
+        Self {
+            all: regions,
+            ids,
+            adjacent,
+        }
+    }
+}
+
+impl<L> Regions<L> {
+    pub fn regions(&self) -> &[Region<L>] {
+        &self.all
+    }
+
+    pub fn region(&self, id: u32) -> &Region<L> {
+        &self.all[id as usize]
+    }
+
+    /// The id of the region containing `x`.
+    pub fn region_of(&self, x: Coordinate) -> u32 {
+        self.ids[x]
+    }
+
+    /// The id of the region containing each tile.
+    pub fn ids(&self) -> &Hexasphere<u32> {
+        &self.ids
+    }
+
+    /// The regions bordering `id`, in increasing order of id,
+    /// each with the number of tile edges along the border.
+    pub fn neighbours(&self, id: u32) -> &[(u32, usize)] {
+        &self.adjacent[id as usize]
+    }
+
+    /// The number of tile edges `a` and `b` share, which is
+    /// zero if they don't touch.
+    pub fn border_length(&self, a: u32, b: u32) -> usize {
+        let list = &self.adjacent[a as usize];
+        list.binary_search_by_key(&b, |&(id, _)| id)
+            .map_or(0, |i| list[i].1)
+    }
+
+    /// Every pair of bordering regions once, lower id
+    /// first, with the length of their border.
+    pub fn borders(&self) -> impl Iterator<Item = (u32, u32, usize)> + '_ {
+        self.adjacent.iter().enumerate().flat_map(|(a, list)| {
+            list.iter()
+                .filter(move |&&(b, _)| a < b as usize)
+                .map(move |&(b, edges)| (a as u32, b, edges))
+        })
+    }
+}
+
+/// What happened to the components when a tile was added or taken away.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ComponentEvent {
+    /// A tile with nothing around it became a new component.
+    Created(u32),
+    /// A tile joined the one component next to it.
+    Grown(u32),
+    /// A tile joined several components, and they are now all `into`.
+    /// The ids in `from` are no longer used.
+    Merged { into: u32, from: Vec<u32> },
+    /// The last tile of a component was taken away.
+    Destroyed(u32),
+    /// A tile was taken away without splitting its component.
+    Shrunk(u32),
+    /// A tile was taken away, and the component it was in fell apart.
+    /// One piece keeps the id `from`, and the others get the new ids
+    /// in `into`.
+    Split { from: u32, into: Vec<u32> },
+}
+
+/// The connected components of a set of tiles, kept up
+/// to date as tiles are added to and taken from the set.
+///
+/// Adding a tile merges the components around it, which is cheap.
+/// Taking one away only searches for a split when the tiles left
+/// around it aren't already connected to each other, and then only
+/// until all but the largest piece have been walked over.
+#[derive(Clone, Debug)]
+pub struct Components {
+    /// Which component each tile was put in, which may
+    /// have since been merged into another.
+    ids: Hexasphere<Option<u32>>,
+    /// The component each was merged into, or itself if it hasn't been.
+    parents: Vec<u32>,
+    sizes: Vec<usize>,
+}
+
+impl Components {
+    /// Starts with the tiles of `sphere` for which `included` is true.
+    pub fn new<T>(sphere: &Hexasphere<T>, included: impl Fn(&T) -> bool) -> Self {
+        let mut components = Self {
+            ids: sphere.change_type(|_| None),
+            parents: Vec::new(),
+            sizes: Vec::new(),
+        };
+
+        for start in sphere.iter_all() {
+            if !included(&sphere[start]) || components.ids[start].is_some() {
+                continue;
+            }
+
+            let id = components.new_id(0);
+            let mut to_explore = vec![start];
+            components.ids[start] = Some(id);
+            while let Some(x) = to_explore.pop() {
+                components.sizes[id as usize] += 1;
+                for around in sphere.surrounding(x) {
+                    if included(&sphere[around]) && components.ids[around].is_none() {
+                        components.ids[around] = Some(id);
+                        to_explore.push(around);
+                    }
+                }
+            }
+        }
+
+        components
+    }
+
+    pub fn contains(&self, x: Coordinate) -> bool {
+        self.ids[x].is_some()
+    }
+
+    /// The id of the component `x` is in, if it's in the set.
+    pub fn component_of(&self, x: Coordinate) -> Option<u32> {
+        self.ids[x].map(|id| self.root(id))
+    }
+
+    /// The number of tiles in component `id`.
+    pub fn len(&self, id: u32) -> usize {
+        self.sizes[self.root(id) as usize]
+    }
+
+    /// The ids of every component.
+    pub fn components(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.parents.len() as u32)
+            .filter(|&id| self.parents[id as usize] == id && self.sizes[id as usize] > 0)
+    }
+
+    /// Adds `x` to the set, or returns `None` if it's already in it.
+    pub fn insert(&mut self, x: Coordinate) -> Option<ComponentEvent> {
+        if self.contains(x) {
+            return None;
+        }
+
+        let mut around = self
+            .ids
+            .surrounding(x)
+            .into_iter()
+            .filter_map(|y| self.component_of(y))
+            .collect::<Vec<_>>();
+        around.sort_unstable();
+        around.dedup();
+
+        let Some(&first) = around.first() else {
+            let id = self.new_id(1);
+            self.ids[x] = Some(id);
+            return Some(ComponentEvent::Created(id));
+        };
+
+        // Merge into the largest, so as few tiles as possible have a longer way to their root.
+        let into = *around
+            .iter()
+            .max_by_key(|&&id| (self.sizes[id as usize], std::cmp::Reverse(id)))
+            .unwrap_or(&first);
+        self.ids[x] = Some(into);
+        self.sizes[into as usize] += 1;
+
+        let from = around
+            .into_iter()
+            .filter(|&id| id != into)
+            .collect::<Vec<_>>();
+        if from.is_empty() {
+            return Some(ComponentEvent::Grown(into));
+        }
+
+        for &id in &from {
+            self.parents[id as usize] = into;
+            self.sizes[into as usize] += std::mem::take(&mut self.sizes[id as usize]);
+        }
+
+        Some(ComponentEvent::Merged { into, from })
+    }
+
+    /// Takes `x` out of the set, or returns `None` if it isn't in it.
+    pub fn remove(&mut self, x: Coordinate) -> Option<ComponentEvent> {
+        let id = self.component_of(x)?;
+        self.ids[x] = None;
+        self.sizes[id as usize] -= 1;
+
+        if self.sizes[id as usize] == 0 {
+            return Some(ComponentEvent::Destroyed(id));
+        }
+
+        // Neighbours next to each other around `x` stay connected, so
+        // only one tile from each unbroken run of them needs checking.
+        let around = self.ids.surrounding(x);
+        let starts = (0..around.len())
+            .filter(|&i| {
+                let before = around[(i + around.len() - 1) % around.len()];
+                self.contains(around[i]) && !self.contains(before)
+            })
+            .map(|i| around[i])
+            .collect::<Vec<_>>();
+
+        if starts.len() < 2 {
+            return Some(ComponentEvent::Shrunk(id));
+        }
+
+        let pieces = self.separate(&starts);
+        if pieces.is_empty() {
+            return Some(ComponentEvent::Shrunk(id));
+        }
+
+        let mut into = Vec::new();
+        for piece in pieces {
+            let new = self.new_id(piece.len());
+            self.sizes[id as usize] -= piece.len();
+            for y in piece {
+                self.ids[y] = Some(new);
+            }
+            into.push(new);
+        }
+
+        Some(ComponentEvent::Split { from: id, into })
+    }
+
+    /// Walks out from each of `starts` at once, merging walks which meet,
+    /// until at most one is still going. Any walk which runs out of tiles
+    /// first has found a piece cut off from the rest.
+    ///
+    /// Returns the tiles of each cut off piece, leaving out
+    /// the largest if every walk ran out.
+    fn separate(&self, starts: &[Coordinate]) -> Vec<HashSet<Coordinate>> {
+        // The tiles each walk has reached, and where it has yet to look.
+        // Walks which have been merged into another are left empty.
+        let mut walks = starts
+            .iter()
+            .map(|&x| (HashSet::from([x]), vec![x]))
+            .collect::<Vec<_>>();
+        let mut merged = vec![false; walks.len()];
+
+        loop {
+            let going = (0..walks.len())
+                .filter(|&i| !merged[i] && !walks[i].1.is_empty())
+                .collect::<Vec<_>>();
+            if going.len() <= 1 {
+                break;
+            }
+
+            for i in going {
+                // Empty if it was merged into another walk earlier on.
+                let Some(x) = walks[i].1.pop() else {
+                    continue;
+                };
+
+                for around in self.ids.surrounding(x) {
+                    if !self.contains(around) || walks[i].0.contains(&around) {
+                        continue;
+                    }
+
+                    if let Some(j) = (0..walks.len()).find(|&j| walks[j].0.contains(&around)) {
+                        let (reached, frontier) = std::mem::take(&mut walks[j]);
+                        walks[i].0.extend(reached);
+                        walks[i].1.extend(frontier);
+                        merged[j] = true;
+                    } else {
+                        walks[i].0.insert(around);
+                        walks[i].1.push(around);
+                    }
+                }
+            }
+        }
+
+        let mut pieces = (0..walks.len())
+            .filter(|&i| !merged[i])
+            .map(|i| std::mem::take(&mut walks[i]))
+            .collect::<Vec<_>>();
+
+        // The piece still going, or else the largest, keeps the old id.
+        let keep = (0..pieces.len())
+            .max_by_key(|&i| (!pieces[i].1.is_empty(), pieces[i].0.len()))
+            .unwrap_or(0);
+        pieces.swap_remove(keep);
+        pieces.into_iter().map(|(reached, _)| reached).collect()
+    }
+
+    fn new_id(&mut self, size: usize) -> u32 {
+        let id = self.parents.len() as u32;
+        self.parents.push(id);
+        self.sizes.push(size);
+        id
+    }
+
+    fn root(&self, mut id: u32) -> u32 {
+        while self.parents[id as usize] != id {
+            id = self.parents[id as usize];
+        }
+        id
+    }
+}